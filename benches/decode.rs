@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use geo_types::Point;
+use nasadem::{NASADEM, SRTM1_SIZE};
+use std::hint::black_box;
+
+const SAMPLES: usize = SRTM1_SIZE * SRTM1_SIZE;
+
+fn decode(c: &mut Criterion) {
+    let hgt: Vec<u8> = (0..SAMPLES)
+        .flat_map(|i| ((i % 4000) as i16 - 400).to_be_bytes())
+        .collect();
+    let swb: Vec<u8> = (0..SAMPLES)
+        .map(|i| if i % 7 == 0 { 255 } else { 0 })
+        .collect();
+
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(20);
+
+    group.throughput(Throughput::Bytes(hgt.len() as u64));
+    group.bench_function("add_elevation", |b| {
+        b.iter(|| {
+            let mut dem = NASADEM::new(Point::new(-106, 38));
+            dem.add_elevation(black_box(&hgt[..])).unwrap();
+            dem
+        })
+    });
+
+    group.throughput(Throughput::Bytes(swb.len() as u64));
+    group.bench_function("add_water", |b| {
+        b.iter(|| {
+            let mut dem = NASADEM::new(Point::new(-106, 38));
+            dem.add_water(black_box(&swb[..])).unwrap();
+            dem
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
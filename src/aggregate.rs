@@ -0,0 +1,411 @@
+//! Aggregation of the samples that fall in each H3 cell.
+
+use crate::{DEMBox, Error, H3Stats, Mosaic, Result, NASADEM};
+use geo_types::Coord;
+use hextree::{
+    compaction::EqCompactor,
+    h3ron::{self, H3Cell},
+    HexTreeMap,
+};
+use std::collections::HashMap;
+
+/// How to combine the elevations of the samples in one H3 cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    Min,
+    Max,
+    Mean,
+    Median,
+    /// The given percentile, from 0 to 100, interpolated linearly
+    /// between the two nearest samples.
+    Percentile(f64),
+}
+
+impl Aggregation {
+    /// Returns an error if this is a percentile outside `0.0..=100.0`.
+    fn validate(self) -> Result<Self> {
+        match self {
+            Aggregation::Percentile(p) if !(0.0..=100.0).contains(&p) => {
+                Err(Error::InvalidPercentile(p))
+            }
+            _ => Ok(self),
+        }
+    }
+
+    /// Returns `true` if this needs every elevation in a cell rather
+    /// than running totals.
+    fn needs_elevations(self) -> bool {
+        matches!(self, Aggregation::Median | Aggregation::Percentile(_))
+    }
+}
+
+/// A cell's aggregated samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellValue {
+    /// Aggregated elevation of the cell's non-void samples, in meters.
+    pub elevation: f64,
+    /// Fraction of the cell's samples that are water, or `None` if
+    /// there is no water layer.
+    pub water_fraction: Option<f32>,
+}
+
+/// Every statistic of a cell's samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellSummary {
+    pub min: i16,
+    pub max: i16,
+    pub mean: f64,
+    pub median: f64,
+    /// Number of non-void samples.
+    pub count: usize,
+    /// Fraction of the cell's samples that are water, or `None` if
+    /// there is no water layer.
+    pub water_fraction: Option<f32>,
+}
+
+impl NASADEM {
+    /// Converts this tile into H3 cells at `resolution`, combining the
+    /// elevations of all the samples in each cell with `aggregation`.
+    ///
+    /// A sample belongs to every cell whose center falls in its
+    /// [`DEMBox::polygon`] or, when the cells are larger than the
+    /// boxes, to the cell containing the sample. Void samples only
+    /// count towards the water fraction, and cells with nothing but
+    /// void samples are left out.
+    ///
+    /// Returns [`Error::InvalidPercentile`] if `aggregation` is a
+    /// percentile outside `0.0..=100.0`.
+    pub fn aggregate_hex_map(
+        &self,
+        resolution: u8,
+        aggregation: Aggregation,
+    ) -> Result<(HexTreeMap<CellValue, EqCompactor>, H3Stats)> {
+        let aggregation = aggregation.validate()?;
+        let mut samples = CellSamples::new(aggregation.needs_elevations());
+        samples.gather(self, resolution, |_, _| true)?;
+        Ok(samples.into_map(|s| s.value(aggregation)))
+    }
+
+    /// Like [`NASADEM::aggregate_hex_map`], but computes every
+    /// statistic at once.
+    ///
+    /// The median needs every elevation in a cell, so this uses as
+    /// much memory as aggregating with [`Aggregation::Median`].
+    pub fn summarize_hex_map(
+        &self,
+        resolution: u8,
+    ) -> Result<(HexTreeMap<CellSummary, EqCompactor>, H3Stats)> {
+        let mut samples = CellSamples::new(true);
+        samples.gather(self, resolution, |_, _| true)?;
+        Ok(samples.into_map(Samples::summary))
+    }
+}
+
+impl Mosaic {
+    /// See [`NASADEM::aggregate_hex_map`].
+    ///
+    /// Samples on an edge shared by two tiles are only counted once.
+    pub fn aggregate_hex_map(
+        &self,
+        resolution: u8,
+        aggregation: Aggregation,
+    ) -> Result<(HexTreeMap<CellValue, EqCompactor>, H3Stats)> {
+        let aggregation = aggregation.validate()?;
+        let samples = self.gather(resolution, aggregation.needs_elevations())?;
+        Ok(samples.into_map(|s| s.value(aggregation)))
+    }
+
+    /// See [`NASADEM::summarize_hex_map`].
+    pub fn summarize_hex_map(
+        &self,
+        resolution: u8,
+    ) -> Result<(HexTreeMap<CellSummary, EqCompactor>, H3Stats)> {
+        let samples = self.gather(resolution, true)?;
+        Ok(samples.into_map(Samples::summary))
+    }
+
+    fn gather(&self, resolution: u8, keep_elevations: bool) -> Result<CellSamples> {
+        let mut samples = CellSamples::new(keep_elevations);
+        for dem in self.sorted_tiles() {
+            samples.gather(dem, resolution, self.owned_samples(dem))?;
+        }
+        Ok(samples)
+    }
+}
+
+/// Samples gathered from one or more tiles, by cell.
+struct CellSamples {
+    cells: HashMap<H3Cell, Samples>,
+    stats: H3Stats,
+    /// Whether to keep each cell's elevations, not just running totals.
+    keep_elevations: bool,
+}
+
+impl CellSamples {
+    fn new(keep_elevations: bool) -> Self {
+        Self {
+            cells: HashMap::new(),
+            stats: H3Stats::default(),
+            keep_elevations,
+        }
+    }
+
+    /// Adds the boxes of `dem` for which `keep(row, col)` is true.
+    fn gather(
+        &mut self,
+        dem: &NASADEM,
+        resolution: u8,
+        keep: impl Fn(usize, usize) -> bool + Sync + Send,
+    ) -> Result<()> {
+        for batch in dem.row_batches() {
+            let converted = dem.map_boxes(batch, |dem_box| {
+                let (row, col) = dem_box.rowcol();
+                if !keep(row, col) {
+                    return Ok(None);
+                }
+                Ok(Some((sample_cells(&dem_box, resolution)?, dem_box)))
+            })?;
+            for (cells, dem_box) in converted.into_iter().flatten() {
+                match dem_box.elevation() {
+                    Some(_) => self.stats.boxes += 1,
+                    None => self.stats.voids += 1,
+                }
+                for cell in cells {
+                    let samples = self.cells.entry(cell).or_default();
+                    samples.add(&dem_box, self.keep_elevations);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn into_map<V: PartialEq + Clone>(
+        self,
+        mut value: impl FnMut(&mut Samples) -> V,
+    ) -> (HexTreeMap<V, EqCompactor>, H3Stats) {
+        let mut stats = self.stats;
+        let mut map = HexTreeMap::with_compactor(EqCompactor);
+        for (cell, mut samples) in self.cells {
+            if samples.count == 0 {
+                continue;
+            }
+            map.insert(cell, value(&mut samples));
+            stats.cells += 1;
+        }
+        stats.compacted_cells = map.len();
+        (map, stats)
+    }
+}
+
+/// The samples in one cell.
+#[derive(Default)]
+struct Samples {
+    min: i16,
+    max: i16,
+    sum: i64,
+    /// Number of non-void samples.
+    count: usize,
+    /// Non-void elevations if they are being kept, sorted once
+    /// aggregation starts.
+    elevations: Vec<i16>,
+    water: usize,
+    /// Samples with a known water flag, including voids.
+    water_known: usize,
+}
+
+impl Samples {
+    fn add(&mut self, dem_box: &DEMBox, keep_elevation: bool) {
+        if let Some(elevation) = dem_box.elevation() {
+            if self.count == 0 {
+                (self.min, self.max) = (elevation, elevation);
+            } else {
+                self.min = self.min.min(elevation);
+                self.max = self.max.max(elevation);
+            }
+            self.sum += i64::from(elevation);
+            self.count += 1;
+            if keep_elevation {
+                self.elevations.push(elevation);
+            }
+        }
+        if let Some(is_water) = dem_box.is_water() {
+            self.water += usize::from(is_water);
+            self.water_known += 1;
+        }
+    }
+
+    fn water_fraction(&self) -> Option<f32> {
+        (self.water_known > 0).then(|| self.water as f32 / self.water_known as f32)
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum as f64 / self.count as f64
+    }
+
+    /// Returns the `p`th percentile; `elevations` must be kept and
+    /// sorted.
+    fn percentile(&self, p: f64) -> f64 {
+        debug_assert!((0.0..=100.0).contains(&p));
+        debug_assert_eq!(self.elevations.len(), self.count);
+        let rank = p / 100.0 * (self.elevations.len() - 1) as f64;
+        let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+        let t = rank - below as f64;
+        f64::from(self.elevations[below]) * (1.0 - t) + f64::from(self.elevations[above]) * t
+    }
+
+    fn value(&mut self, aggregation: Aggregation) -> CellValue {
+        let elevation = match aggregation {
+            Aggregation::Min => f64::from(self.min),
+            Aggregation::Max => f64::from(self.max),
+            Aggregation::Mean => self.mean(),
+            Aggregation::Median => {
+                self.elevations.sort_unstable();
+                self.percentile(50.0)
+            }
+            Aggregation::Percentile(p) => {
+                self.elevations.sort_unstable();
+                self.percentile(p)
+            }
+        };
+        CellValue {
+            elevation,
+            water_fraction: self.water_fraction(),
+        }
+    }
+
+    fn summary(&mut self) -> CellSummary {
+        self.elevations.sort_unstable();
+        CellSummary {
+            min: self.min,
+            max: self.max,
+            mean: self.mean(),
+            median: self.percentile(50.0),
+            count: self.count,
+            water_fraction: self.water_fraction(),
+        }
+    }
+}
+
+/// Returns the cells whose centers fall in `dem_box`, or the cell
+/// containing its sample if there are none.
+fn sample_cells(dem_box: &DEMBox, resolution: u8) -> Result<Vec<H3Cell>> {
+    let cells = h3ron::polygon_to_cells(&dem_box.polygon(), resolution)?;
+    if !cells.is_empty() {
+        return Ok(cells.iter().collect());
+    }
+    let center = Coord::from(dem_box.center());
+    Ok(vec![H3Cell::from_coordinate(center, resolution)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_support::test_tile, VOID};
+    use geo_types::Point;
+
+    /// A 5×5 tile whose samples are `idx`, with a void at
+    /// 12 and water on the top row.
+    fn tile(southwest_corner: Point<i32>) -> NASADEM {
+        let mut dem = test_tile(5, southwest_corner, |row, col| match (row, col) {
+            (2, 2) => VOID,
+            _ => (5 * row + col) as i16,
+        });
+        let swb: Vec<u8> = (0..25).map(|idx| if idx < 5 { 255 } else { 0 }).collect();
+        dem.add_water(&swb[..]).unwrap();
+        dem
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let dem = tile(Point::new(-100, 40));
+        // Resolution 0 cells are far larger than the tile, so every
+        // sample lands in the same one.
+        let (map, stats) = dem.aggregate_hex_map(0, Aggregation::Min).unwrap();
+        assert_eq!(stats.boxes, 24);
+        assert_eq!(stats.voids, 1);
+        assert_eq!(stats.cells, 1);
+        let (cell, value) = map.iter().next().unwrap();
+        let cell = *cell;
+        assert_eq!(value.elevation, 0.0);
+        assert_eq!(value.water_fraction, Some(5.0 / 25.0));
+
+        let elevation = |aggregation| {
+            let (map, _) = dem.aggregate_hex_map(0, aggregation).unwrap();
+            map.get(cell).unwrap().elevation
+        };
+        assert_eq!(elevation(Aggregation::Max), 24.0);
+        assert_eq!(elevation(Aggregation::Mean), (300.0 - 12.0) / 24.0);
+        assert_eq!(elevation(Aggregation::Median), 12.0);
+        assert_eq!(elevation(Aggregation::Percentile(100.0)), 24.0);
+        assert!((elevation(Aggregation::Percentile(10.0)) - 2.3).abs() < 1e-9);
+
+        let (map, _) = dem.summarize_hex_map(0).unwrap();
+        let summary = map.get(cell).unwrap();
+        assert_eq!((summary.min, summary.max, summary.count), (0, 24, 24));
+        assert_eq!(summary.median, 12.0);
+    }
+
+    #[test]
+    fn test_running_totals_keep_no_elevations() {
+        let dem = tile(Point::new(-100, 40));
+        let mut samples = CellSamples::new(Aggregation::Mean.needs_elevations());
+        samples.gather(&dem, 0, |_, _| true).unwrap();
+        let cell = samples.cells.values().next().unwrap();
+        assert_eq!((cell.min, cell.max, cell.count), (0, 24, 24));
+        assert!(cell.elevations.is_empty());
+    }
+
+    #[test]
+    fn test_fine_resolution_matches_polygon_fill() {
+        let dem = tile(Point::new(10, 40));
+        let (map, stats) = dem.aggregate_hex_map(5, Aggregation::Mean).unwrap();
+        let (plain, plain_stats) = dem.to_hex_map(5).unwrap();
+        assert_eq!(stats.cells, plain_stats.cells);
+        for (cell, value) in plain.iter() {
+            assert_eq!(map.get(*cell).unwrap().elevation, f64::from(*value));
+        }
+    }
+
+    #[test]
+    fn test_mosaic_counts_shared_edges_once() {
+        let mosaic: Mosaic = [Point::new(10, 40), Point::new(11, 40)]
+            .into_iter()
+            .map(tile)
+            .collect();
+        let (map, stats) = mosaic.summarize_hex_map(0).unwrap();
+        assert_eq!(stats.boxes + stats.voids, 45);
+        let count: usize = map.iter().map(|(_, s)| s.count).sum();
+        assert_eq!(count, stats.boxes);
+    }
+
+    #[test]
+    fn test_mosaic_counts_l_shaped_corner_once() {
+        // Three 3×3 tiles in an L share the point (1, 0); no tile sits
+        // diagonally southeast of the corner tile.
+        let flat = |corner| test_tile(3, corner, |_, _| 0);
+        let mosaic: Mosaic = [Point::new(0, 0), Point::new(1, 0), Point::new(0, -1)]
+            .into_iter()
+            .map(flat)
+            .collect();
+        let (map, stats) = mosaic.summarize_hex_map(0).unwrap();
+        assert_eq!(stats.boxes, 21);
+        let count: usize = map.iter().map(|(_, s)| s.count).sum();
+        assert_eq!(count, 21);
+    }
+
+    #[test]
+    fn test_invalid_percentile() {
+        let dem = tile(Point::new(10, 40));
+        for p in [101.0, -1.0, f64::NAN] {
+            assert!(matches!(
+                dem.aggregate_hex_map(0, Aggregation::Percentile(p)),
+                Err(Error::InvalidPercentile(_))
+            ));
+        }
+        let mosaic: Mosaic = [dem].into_iter().collect();
+        assert!(matches!(
+            mosaic.aggregate_hex_map(0, Aggregation::Percentile(f64::NAN)),
+            Err(Error::InvalidPercentile(_))
+        ));
+    }
+}
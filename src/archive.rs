@@ -0,0 +1,124 @@
+//! Reading layers straight out of `NASADEM_HGT_*.zip` archives.
+
+use crate::{parse_tile_name, Error, Result, NASADEM};
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::Path,
+};
+use zip::ZipArchive;
+
+impl NASADEM {
+    /// Opens a NASADEM archive such as `NASADEM_HGT_n38w106.zip` and
+    /// adds every layer it contains.
+    ///
+    /// The tile's southwest corner is parsed from the archive's name,
+    /// falling back to the names of its members.
+    pub fn from_zip(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+        let southwest_corner = match parse_tile_name(path) {
+            Ok(corner) => corner,
+            Err(e) => archive
+                .file_names()
+                .find_map(|name| parse_tile_name(name.ok()?.as_ref()).ok())
+                .ok_or(e)?,
+        };
+        let mut dem = Self::new(southwest_corner);
+        dem.add_archive(&mut archive)?;
+        Ok(dem)
+    }
+
+    /// Adds every layer contained in a NASADEM zip archive.
+    ///
+    /// Members are recognized by extension: `.hgt` for elevation,
+    /// `.swb` for water and `.num` for source. Anything else is
+    /// ignored, but an archive with none of these is an
+    /// [`Error::NoLayers`].
+    pub fn add_zip(&mut self, src: impl Read + Seek) -> Result<&mut Self> {
+        self.add_archive(&mut ZipArchive::new(src)?)
+    }
+
+    fn add_archive<R: Read + Seek>(&mut self, archive: &mut ZipArchive<R>) -> Result<&mut Self> {
+        let mut found_layer = false;
+        for i in 0..archive.len() {
+            let member = archive.by_index(i)?;
+            let extension = Path::new(member.name()?.as_ref())
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_ascii_lowercase());
+            match extension.as_deref() {
+                Some("hgt") => self.add_elevation(member)?,
+                Some("swb") => self.add_water(member)?,
+                Some("num") => self.add_source(member)?,
+                _ => continue,
+            };
+            found_layer = true;
+        }
+        if !found_layer {
+            return Err(Error::NoLayers);
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Source;
+    use geo_types::Point;
+    use std::io::{Cursor, Write};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in members {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_add_zip() {
+        let hgt: Vec<u8> = (0..9_i16).flat_map(|s| s.to_be_bytes()).collect();
+        let swb = [0, 0, 0, 0, 255, 0, 0, 0, 0];
+        let num = [11, 11, 11, 11, 5, 11, 11, 11, 11];
+        let zip = archive(&[
+            ("n38w106.hgt", &hgt),
+            ("n38w106.swb", &swb),
+            ("n38w106.num", &num),
+            ("n38w106.txt", b"ignored"),
+        ]);
+
+        let mut dem = NASADEM::new(Point::new(-106, 38));
+        dem.add_zip(Cursor::new(zip)).unwrap();
+        let center = dem.iter().nth(4).unwrap();
+        assert_eq!(dem.geometry().cols(), 3);
+        assert_eq!(center.elevation(), Some(4));
+        assert_eq!(center.is_water(), Some(true));
+        assert_eq!(center.source(), Some(Source::Interpolated));
+    }
+
+    #[test]
+    fn test_from_zip() {
+        let hgt = [0_u8; 2 * 2 * 2];
+        let path = std::env::temp_dir().join("NASADEM_HGT_s01e010.zip");
+        std::fs::write(&path, archive(&[("s01e010.hgt", &hgt)])).unwrap();
+        let dem = NASADEM::from_zip(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(dem.geometry().origin(), &Point::new(10.0, 0.0));
+        assert_eq!(dem.void_count(), Some(0));
+    }
+
+    #[test]
+    fn test_add_zip_without_layers() {
+        let zip = archive(&[("readme.txt", b"nothing here")]);
+        let err = NASADEM::new(Point::new(-106, 38))
+            .add_zip(Cursor::new(zip))
+            .unwrap_err();
+        assert!(matches!(err, Error::NoLayers));
+    }
+}
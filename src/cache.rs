@@ -0,0 +1,287 @@
+//! On-demand tile loading with a bounded memory footprint.
+
+use crate::{
+    mosaic::{candidate_tiles, tiles_covering},
+    tile_name, Interpolation, Mosaic, Result, NASADEM,
+};
+use geo_types::{Point, Rect};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+/// Hit/miss counters for a [`TileCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Tile lookups answered without touching the file system,
+    /// including lookups of tiles already known to be missing.
+    pub hits: u64,
+    /// Tile lookups that had to search `dir` and load the tile.
+    pub misses: u64,
+    /// Tiles dropped to stay within the memory budget.
+    pub evictions: u64,
+}
+
+/// Loads tiles from a directory the first time they're queried.
+///
+/// Once the loaded tiles use more than `budget` bytes (see
+/// [`NASADEM::heap_size`]), the least recently used ones are evicted.
+/// Tiles needed by the query in progress are never evicted, so a
+/// single query that spans several tiles may briefly exceed the
+/// budget.
+///
+/// For a tile named `n38w106`, `dir` is searched for
+/// `n38w106.hgt`, `N38W106.hgt`, `NASADEM_HGT_n38w106/n38w106.hgt`
+/// and, with the `zip` feature, `NASADEM_HGT_n38w106.zip`. Any `.swb`
+/// and `.num` layers next to an `.hgt` are loaded with it.
+#[derive(Debug)]
+pub struct TileCache {
+    dir: PathBuf,
+    budget: usize,
+    heap_size: usize,
+    mosaic: Mosaic,
+    /// Value of `clock` when each loaded tile was last used.
+    last_used: HashMap<(i32, i32), u64>,
+    /// Tiles known not to exist in `dir`.
+    absent: HashSet<(i32, i32)>,
+    /// Incremented once per query.
+    clock: u64,
+    stats: CacheStats,
+}
+
+impl TileCache {
+    pub fn new(dir: impl Into<PathBuf>, budget: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            budget,
+            heap_size: 0,
+            mosaic: Mosaic::new(),
+            last_used: HashMap::new(),
+            absent: HashSet::new(),
+            clock: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Returns the number of bytes used by the loaded tiles.
+    pub fn heap_size(&self) -> usize {
+        self.heap_size
+    }
+
+    /// Returns the tiles currently in memory.
+    pub fn loaded(&self) -> &Mosaic {
+        &self.mosaic
+    }
+
+    /// Returns the tile with the given southwest corner, loading it if
+    /// needed, or `None` if `dir` doesn't have it.
+    pub fn get(&mut self, southwest_corner: &Point<i32>) -> Result<Option<&NASADEM>> {
+        self.clock += 1;
+        self.touch(southwest_corner)?;
+        Ok(self.mosaic.get(southwest_corner))
+    }
+
+    /// See [`NASADEM::elevation_at`].
+    pub fn elevation_at(&mut self, point: Point<f64>) -> Result<Option<i16>> {
+        self.clock += 1;
+        self.touch_containing(point)?;
+        Ok(self.mosaic.elevation_at(point))
+    }
+
+    /// See [`NASADEM::water_at`].
+    pub fn water_at(&mut self, point: Point<f64>) -> Result<Option<bool>> {
+        self.clock += 1;
+        self.touch_containing(point)?;
+        Ok(self.mosaic.water_at(point))
+    }
+
+    /// See [`Mosaic::sample`].
+    pub fn sample(&mut self, point: Point<f64>, interp: Interpolation) -> Result<Option<f64>> {
+        self.clock += 1;
+        let Some(spacing) = self.touch_containing(point)? else {
+            return Ok(None);
+        };
+        // Bicubic reaches up to two samples away.
+        let margin = 2.0 * spacing;
+        let (x, y) = point.x_y();
+        let neighbourhood = Rect::new((x - margin, y - margin), (x + margin, y + margin));
+        for corner in tiles_covering(neighbourhood) {
+            self.touch(&corner)?;
+        }
+        Ok(self.mosaic.sample(point, interp))
+    }
+
+    /// Loads the first available tile containing `point`, returning
+    /// its sample spacing.
+    fn touch_containing(&mut self, point: Point<f64>) -> Result<Option<f64>> {
+        for corner in candidate_tiles(point) {
+            self.touch(&corner)?;
+            if let Some(dem) = self.mosaic.get(&corner) {
+                return Ok(Some(dem.geometry().spacing()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Marks a tile as used by the current query, loading it if
+    /// needed.
+    fn touch(&mut self, southwest_corner: &Point<i32>) -> Result<()> {
+        let key = southwest_corner.x_y();
+        if self.absent.contains(&key) {
+            self.stats.hits += 1;
+            return Ok(());
+        }
+        if let Some(last_used) = self.last_used.get_mut(&key) {
+            *last_used = self.clock;
+            self.stats.hits += 1;
+            return Ok(());
+        }
+        self.stats.misses += 1;
+        match load_tile(&self.dir, southwest_corner)? {
+            Some(dem) => {
+                self.heap_size += dem.heap_size();
+                self.mosaic.insert(dem);
+                self.last_used.insert(key, self.clock);
+                self.evict();
+            }
+            None => {
+                self.absent.insert(key);
+            }
+        }
+        Ok(())
+    }
+
+    fn evict(&mut self) {
+        while self.heap_size > self.budget {
+            let Some(key) = self
+                .last_used
+                .iter()
+                .filter(|(_, &last_used)| last_used < self.clock)
+                .min_by_key(|(_, &last_used)| last_used)
+                .map(|(&key, _)| key)
+            else {
+                break;
+            };
+            self.last_used.remove(&key);
+            if let Some(dem) = self.mosaic.remove(&Point::new(key.0, key.1)) {
+                self.heap_size -= dem.heap_size();
+            }
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+/// Loads every layer `dir` has for a tile.
+fn load_tile(dir: &Path, southwest_corner: &Point<i32>) -> Result<Option<NASADEM>> {
+    let name = tile_name(southwest_corner);
+    let archive_dir = dir.join(format!("NASADEM_HGT_{name}"));
+    for (dir, stem) in [
+        (dir, name.clone()),
+        (dir, name.to_ascii_uppercase()),
+        (&*archive_dir, name.clone()),
+    ] {
+        let path = |ext: &str| Some(dir.join(format!("{stem}.{ext}"))).filter(|p| p.is_file());
+        let Some(hgt) = path("hgt") else {
+            continue;
+        };
+        let mut dem = NASADEM::new(*southwest_corner);
+        dem.add_elevation(BufReader::new(File::open(hgt)?))?;
+        if let Some(swb) = path("swb") {
+            dem.add_water(BufReader::new(File::open(swb)?))?;
+        }
+        if let Some(num) = path("num") {
+            dem.add_source(BufReader::new(File::open(num)?))?;
+        }
+        return Ok(Some(dem));
+    }
+    #[cfg(feature = "zip")]
+    {
+        let zip = dir.join(format!("NASADEM_HGT_{name}.zip"));
+        if zip.is_file() {
+            let mut dem = NASADEM::new(*southwest_corner);
+            dem.add_zip(BufReader::new(File::open(zip)?))?;
+            return Ok(Some(dem));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Writes a 3×3 tile whose samples all equal `elevation`.
+    fn write_tile(dir: &Path, name: &str, elevation: i16) {
+        let hgt: Vec<u8> = [elevation; 9]
+            .iter()
+            .flat_map(|s| s.to_be_bytes())
+            .collect();
+        fs::write(dir.join(name), hgt).unwrap();
+    }
+
+    fn tile_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nasadem-cache-{test}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("NASADEM_HGT_n01e000")).unwrap();
+        write_tile(&dir, "n00e000.hgt", 100);
+        write_tile(&dir, "N00E001.hgt", 200);
+        write_tile(&dir.join("NASADEM_HGT_n01e000"), "n01e000.hgt", 300);
+        fs::write(dir.join("n00e000.swb"), [255_u8; 9]).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_lazy_loading() {
+        let dir = tile_dir("lazy");
+        let mut cache = TileCache::new(&dir, usize::MAX);
+        assert_eq!(cache.elevation_at(Point::new(0.5, 0.5)).unwrap(), Some(100));
+        assert_eq!(cache.water_at(Point::new(0.5, 0.5)).unwrap(), Some(true));
+        assert_eq!(cache.elevation_at(Point::new(1.5, 0.5)).unwrap(), Some(200));
+        assert_eq!(cache.elevation_at(Point::new(0.5, 1.5)).unwrap(), Some(300));
+        assert_eq!(cache.elevation_at(Point::new(5.5, 5.5)).unwrap(), None);
+        assert_eq!(cache.elevation_at(Point::new(5.5, 5.5)).unwrap(), None);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 4,
+                evictions: 0
+            }
+        );
+        assert_eq!(cache.loaded().len(), 3);
+        assert_eq!(cache.heap_size(), 3 * 18 + 24);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let dir = tile_dir("evict");
+        // Room for two elevation-only tiles, or the one with water.
+        let mut cache = TileCache::new(&dir, 42);
+        cache.get(&Point::new(1, 0)).unwrap();
+        cache.get(&Point::new(0, 1)).unwrap();
+        cache.get(&Point::new(1, 0)).unwrap();
+        assert_eq!(cache.elevation_at(Point::new(0.5, 0.5)).unwrap(), Some(100));
+        assert_eq!(cache.stats().evictions, 2);
+        assert!(cache.loaded().get(&Point::new(0, 0)).is_some());
+        assert!(cache.loaded().get(&Point::new(1, 0)).is_none());
+        assert!(cache.loaded().get(&Point::new(0, 1)).is_none());
+        assert_eq!(cache.heap_size(), 42);
+
+        // A query spanning two tiles keeps both while it runs.
+        let mut cache = TileCache::new(&dir, 0);
+        let z = cache
+            .sample(Point::new(0.9, 0.5), Interpolation::Bicubic)
+            .unwrap()
+            .unwrap();
+        assert!((z - 100.0).abs() > 1.0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
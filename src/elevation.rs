@@ -0,0 +1,58 @@
+//! Storage for the elevation layer.
+
+/// A tile's elevation samples, either decoded into memory or read
+/// in place from a memory-mapped HGT file.
+#[derive(Debug)]
+pub(crate) enum Elevation {
+    Owned {
+        samples: Vec<i16>,
+        void_count: usize,
+    },
+    /// Raw big-endian HGT bytes, decoded on access.
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Elevation {
+    pub(crate) fn get(&self, idx: usize) -> i16 {
+        match self {
+            Elevation::Owned { samples, .. } => samples[idx],
+            #[cfg(feature = "mmap")]
+            Elevation::Mapped(map) => i16::from_be_bytes([map[2 * idx], map[2 * idx + 1]]),
+        }
+    }
+
+    /// Returns the decoded samples, or `None` for mapped files.
+    pub(crate) fn as_slice(&self) -> Option<&[i16]> {
+        match self {
+            Elevation::Owned { samples, .. } => Some(samples),
+            #[cfg(feature = "mmap")]
+            Elevation::Mapped(_) => None,
+        }
+    }
+
+    /// Returns the number of void samples.
+    ///
+    /// This scans the whole layer for mapped files.
+    pub(crate) fn void_count(&self) -> usize {
+        match self {
+            Elevation::Owned { void_count, .. } => *void_count,
+            #[cfg(feature = "mmap")]
+            Elevation::Mapped(map) => map
+                .chunks_exact(2)
+                .filter(|b| i16::from_be_bytes([b[0], b[1]]) == crate::VOID)
+                .count(),
+        }
+    }
+
+    /// Returns the number of bytes this layer holds on the heap.
+    ///
+    /// Mapped pages belong to the OS page cache and aren't counted.
+    pub(crate) fn heap_size(&self) -> usize {
+        match self {
+            Elevation::Owned { samples, .. } => samples.len() * 2,
+            #[cfg(feature = "mmap")]
+            Elevation::Mapped(_) => 0,
+        }
+    }
+}
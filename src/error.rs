@@ -0,0 +1,107 @@
+//! Errors returned while reading DEM layers.
+
+use std::{error::Error as StdError, fmt, io, path::PathBuf};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Variants for optional features only exist when the feature is
+/// enabled, so this enum is non-exhaustive.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading from the underlying source failed.
+    Io(io::Error),
+    /// A layer ended before its last sample.
+    Truncated { expected: usize, got: usize },
+    /// A layer has more bytes than the tile has samples.
+    Oversized { expected: usize, got: usize },
+    /// A `.swb` sample other than 0 (land) or 255 (water).
+    InvalidWaterValue { idx: usize, value: u8 },
+    /// A file name that doesn't identify a tile.
+    BadFilename(PathBuf),
+    /// Reading a zip archive failed.
+    #[cfg(feature = "zip")]
+    Zip(zip::result::ZipError),
+    /// A zip archive has no `.hgt`, `.swb` or `.num` member.
+    #[cfg(feature = "zip")]
+    NoLayers,
+    /// An H3 operation failed, e.g. because of an invalid resolution.
+    #[cfg(feature = "h3")]
+    H3(hextree::h3ron::Error),
+    /// A percentile aggregation outside `0.0..=100.0`, or NaN.
+    #[cfg(feature = "h3")]
+    InvalidPercentile(f64),
+    /// Encoding an image failed.
+    #[cfg(feature = "image")]
+    Image(image::ImageError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::Truncated { expected, got } => {
+                write!(f, "layer truncated: expected {expected} bytes, got {got}")
+            }
+            Error::Oversized { expected, got } => {
+                write!(f, "layer oversized: expected {expected} bytes, got {got}")
+            }
+            Error::InvalidWaterValue { idx, value } => {
+                write!(f, "invalid water value {value} at sample {idx}")
+            }
+            Error::BadFilename(path) => write!(f, "not a tile file name: {}", path.display()),
+            #[cfg(feature = "zip")]
+            Error::Zip(e) => e.fmt(f),
+            #[cfg(feature = "zip")]
+            Error::NoLayers => f.write_str("archive has no .hgt, .swb or .num member"),
+            #[cfg(feature = "h3")]
+            Error::H3(e) => e.fmt(f),
+            #[cfg(feature = "h3")]
+            Error::InvalidPercentile(p) => write!(f, "percentile {p} is not in 0..=100"),
+            #[cfg(feature = "image")]
+            Error::Image(e) => e.fmt(f),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            #[cfg(feature = "zip")]
+            Error::Zip(e) => Some(e),
+            #[cfg(feature = "h3")]
+            Error::H3(e) => Some(e),
+            #[cfg(feature = "image")]
+            Error::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "zip")]
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Error::Zip(e)
+    }
+}
+
+#[cfg(feature = "h3")]
+impl From<hextree::h3ron::Error> for Error {
+    fn from(e: hextree::h3ron::Error) -> Self {
+        Error::H3(e)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
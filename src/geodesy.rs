@@ -0,0 +1,59 @@
+//! Distances on the Earth's surface.
+
+use geo_types::Point;
+use std::f64::consts::PI;
+
+/// Mean radius of the Earth in meters, treating it as a sphere.
+pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Returns the ground length in meters of one degree of longitude and
+/// one degree of latitude at `latitude`.
+pub(crate) fn meters_per_degree(latitude: f64) -> (f64, f64) {
+    let meridian = EARTH_RADIUS * PI / 180.0;
+    (meridian * latitude.to_radians().cos(), meridian)
+}
+
+/// Returns the great-circle distance between `a` and `b` in meters.
+pub(crate) fn distance(a: Point<f64>, b: Point<f64>) -> f64 {
+    let (lat_a, lat_b) = (a.y().to_radians(), b.y().to_radians());
+    let (dlat, dlon) = (lat_b - lat_a, (b.x() - a.x()).to_radians());
+    let h = (dlat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+/// Returns the point `fraction` of the way from `a` to `b` along the
+/// great circle between them.
+pub(crate) fn intermediate_point(a: Point<f64>, b: Point<f64>, fraction: f64) -> Point<f64> {
+    let angle = distance(a, b) / EARTH_RADIUS;
+    if angle == 0.0 {
+        return a;
+    }
+    let to_vector = |p: Point<f64>| {
+        let (lat, lon) = (p.y().to_radians(), p.x().to_radians());
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+    };
+    let (va, vb) = (to_vector(a), to_vector(b));
+    let wa = ((1.0 - fraction) * angle).sin() / angle.sin();
+    let wb = (fraction * angle).sin() / angle.sin();
+    let [x, y, z] = [0, 1, 2].map(|i| wa * va[i] + wb * vb[i]);
+    Point::new(y.atan2(x).to_degrees(), z.atan2(x.hypot(y)).to_degrees())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        // A degree of longitude on the equator.
+        let d = distance(Point::new(0.0, 0.0), Point::new(1.0, 0.0));
+        assert!((d - EARTH_RADIUS * PI / 180.0).abs() < 1e-6);
+        let (x, y) = meters_per_degree(60.0);
+        assert!((x - y / 2.0).abs() < 1e-6);
+
+        let (a, b) = (Point::new(-105.0, 40.0), Point::new(2.35, 48.85));
+        let middle = intermediate_point(a, b, 0.5);
+        assert!((distance(a, middle) - distance(middle, b)).abs() < 1e-3);
+        assert_eq!(intermediate_point(a, a, 0.3), a);
+    }
+}
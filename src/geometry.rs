@@ -0,0 +1,361 @@
+//! Georeferencing of a DEM sample grid.
+
+use geo_types::{LineString, Point, Polygon, Rect};
+use std::ops::Range;
+
+/// How samples relate to the grid they are laid out on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Registration {
+    /// Samples sit on grid intersections (pixel-is-point).
+    ///
+    /// This is what HGT files use: the first and last row/column lie
+    /// exactly on the tile's edges, and each sample's cell extends
+    /// half a spacing to either side of it.
+    Point,
+    /// Samples represent the cell to the southeast of a grid
+    /// intersection (pixel-is-area).
+    Area,
+}
+
+/// Maps grid `(row, col)` indices to geographic coordinates.
+///
+/// Rows run north to south and columns west to east, matching the
+/// layout of HGT files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    /// Northwest-most grid intersection.
+    origin: Point<f64>,
+    /// Distance between adjacent samples in degrees.
+    spacing: f64,
+    registration: Registration,
+    rows: usize,
+    cols: usize,
+}
+
+impl Geometry {
+    pub fn new(
+        origin: Point<f64>,
+        spacing: f64,
+        registration: Registration,
+        rows: usize,
+        cols: usize,
+    ) -> Self {
+        Self {
+            origin,
+            spacing,
+            registration,
+            rows,
+            cols,
+        }
+    }
+
+    /// Returns the geometry of a 1°×1° HGT tile with `size` samples
+    /// per side, e.g. 3601 for 1 arc-second or 1201 for 3 arc-second
+    /// data.
+    pub fn tile(southwest_corner: &Point<i32>, size: usize) -> Self {
+        debug_assert!(size >= 2);
+        Self::new(
+            Point::new(
+                southwest_corner.x() as f64,
+                southwest_corner.y() as f64 + 1.0,
+            ),
+            1.0 / (size - 1) as f64,
+            Registration::Point,
+            size,
+            size,
+        )
+    }
+
+    pub fn origin(&self) -> &Point<f64> {
+        &self.origin
+    }
+
+    pub fn spacing(&self) -> f64 {
+        self.spacing
+    }
+
+    pub fn registration(&self) -> Registration {
+        self.registration
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the total number of samples in the grid.
+    pub fn len(&self) -> usize {
+        self.rows * self.cols
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Converts a row-major sample index to `(row, col)`.
+    pub fn idx_to_rowcol(&self, idx: usize) -> (usize, usize) {
+        debug_assert!(idx < self.len());
+        (idx / self.cols, idx % self.cols)
+    }
+
+    /// Returns `(row, col)` as unsigned indices if it's inside the
+    /// grid.
+    pub fn checked_rowcol(&self, row: isize, col: isize) -> Option<(usize, usize)> {
+        let row = usize::try_from(row).ok().filter(|&r| r < self.rows)?;
+        let col = usize::try_from(col).ok().filter(|&c| c < self.cols)?;
+        Some((row, col))
+    }
+
+    /// Converts `(row, col)` to a row-major sample index.
+    pub fn rowcol_to_idx(&self, row: usize, col: usize) -> usize {
+        debug_assert!(row < self.rows && col < self.cols);
+        row * self.cols + col
+    }
+
+    /// Returns the location of the sample at `(row, col)`.
+    ///
+    /// For [`Registration::Area`] this is the center of the cell.
+    pub fn sample_point(&self, row: usize, col: usize) -> Point<f64> {
+        self.fractional_point(row as f64, col as f64)
+    }
+
+    /// Returns the location of fractional `(row, col)`, which may lie
+    /// outside the grid.
+    pub fn fractional_point(&self, row: f64, col: f64) -> Point<f64> {
+        let offset = match self.registration {
+            Registration::Point => 0.0,
+            Registration::Area => 0.5,
+        };
+        Point::new(
+            self.origin.x() + (col + offset) * self.spacing,
+            self.origin.y() - (row + offset) * self.spacing,
+        )
+    }
+
+    /// Returns the extent covered by this grid.
+    ///
+    /// For [`Registration::Point`] this runs from the first sample to
+    /// the last, so a tile's bounds are exactly its 1°×1° square. For
+    /// [`Registration::Area`] it runs from the outer corner of the
+    /// first cell to that of the last.
+    pub fn bounds(&self) -> Rect<f64> {
+        let (rows, cols) = (self.rows.max(1) - 1, self.cols.max(1) - 1);
+        match self.registration {
+            Registration::Point => {
+                Rect::new(self.sample_point(0, 0), self.sample_point(rows, cols))
+            }
+            Registration::Area => {
+                let (first, last) = (self.cell_rect(0, 0), self.cell_rect(rows, cols));
+                Rect::new((first.min().x, first.max().y), (last.max().x, last.min().y))
+            }
+        }
+    }
+
+    /// Returns the fractional `(row, col)` at which `point` falls, the
+    /// inverse of [`Geometry::fractional_point`].
+    ///
+    /// The result is not bounds checked.
+    pub fn fractional_rowcol(&self, point: &Point<f64>) -> (f64, f64) {
+        let offset = match self.registration {
+            Registration::Point => 0.0,
+            Registration::Area => 0.5,
+        };
+        (
+            (self.origin.y() - point.y()) / self.spacing - offset,
+            (point.x() - self.origin.x()) / self.spacing - offset,
+        )
+    }
+
+    /// Returns the `(row, col)` of the sample nearest to `point`, or
+    /// `None` if `point` is outside [`Geometry::bounds`].
+    pub fn nearest_rowcol(&self, point: &Point<f64>) -> Option<(usize, usize)> {
+        let bounds = self.bounds();
+        let (x, y) = point.x_y();
+        if !(bounds.min().x <= x
+            && x <= bounds.max().x
+            && bounds.min().y <= y
+            && y <= bounds.max().y)
+        {
+            return None;
+        }
+        let (row, col) = self.fractional_rowcol(point);
+        Some((
+            (row.round().max(0.0) as usize).min(self.rows - 1),
+            (col.round().max(0.0) as usize).min(self.cols - 1),
+        ))
+    }
+
+    /// Returns the rows and columns of the cells that intersect
+    /// `window`, or `None` if none do.
+    pub fn window(&self, window: &Rect<f64>) -> Option<(Range<usize>, Range<usize>)> {
+        // Each cell reaches half a spacing past its sample.
+        let (north, west) = self.fractional_rowcol(&Point::new(window.min().x, window.max().y));
+        let (south, east) = self.fractional_rowcol(&Point::new(window.max().x, window.min().y));
+        let span = |first: f64, last: f64, len: usize| {
+            let first = (first - 0.5).ceil().max(0.0) as usize;
+            let end = ((last + 0.5).floor() + 1.0).clamp(0.0, len as f64) as usize;
+            Some(first..end).filter(|span| !span.is_empty())
+        };
+        Some((span(north, south, self.rows)?, span(west, east, self.cols)?))
+    }
+
+    /// Returns the geometry of the given rows and columns of this grid.
+    pub fn subgrid(&self, rows: Range<usize>, cols: Range<usize>) -> Self {
+        let offset = match self.registration {
+            Registration::Point => 0.0,
+            Registration::Area => 0.5,
+        };
+        let first = self.sample_point(rows.start, cols.start);
+        let origin = Point::new(
+            first.x() - offset * self.spacing,
+            first.y() + offset * self.spacing,
+        );
+        Self::new(
+            origin,
+            self.spacing,
+            self.registration,
+            rows.len(),
+            cols.len(),
+        )
+    }
+
+    /// Returns the area represented by the sample at `(row, col)`.
+    pub fn cell_rect(&self, row: usize, col: usize) -> Rect<f64> {
+        let center = self.sample_point(row, col);
+        let half = self.spacing / 2.0;
+        Rect::new(
+            (center.x() - half, center.y() - half),
+            (center.x() + half, center.y() + half),
+        )
+    }
+
+    /// Returns the area represented by the sample at `(row, col)` as
+    /// a closed polygon, wound counter-clockwise from its southwest
+    /// corner.
+    pub fn cell_polygon(&self, row: usize, col: usize) -> Polygon {
+        let rect = self.cell_rect(row, col);
+        let (lon_west, lat_south) = rect.min().x_y();
+        let (lon_east, lat_north) = rect.max().x_y();
+        Polygon::new(
+            LineString::from(vec![
+                (lon_west, lat_south),
+                (lon_east, lat_south),
+                (lon_east, lat_north),
+                (lon_west, lat_north),
+                (lon_west, lat_south),
+            ]),
+            Vec::new(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Point<f64>, b: Point<f64>) {
+        assert!(
+            (a.x() - b.x()).abs() < 1e-9 && (a.y() - b.y()).abs() < 1e-9,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn test_srtm1_corners() {
+        let geo = Geometry::tile(&Point::new(-106, 38), 3601);
+        assert_close(geo.sample_point(0, 0), Point::new(-106.0, 39.0));
+        assert_close(geo.sample_point(0, 3600), Point::new(-105.0, 39.0));
+        assert_close(geo.sample_point(3600, 0), Point::new(-106.0, 38.0));
+        assert_close(geo.sample_point(3600, 3600), Point::new(-105.0, 38.0));
+        assert_close(geo.sample_point(1800, 1800), Point::new(-105.5, 38.5));
+    }
+
+    #[test]
+    fn test_srtm3_corners() {
+        let geo = Geometry::tile(&Point::new(-106, 38), 1201);
+        assert_close(geo.sample_point(0, 0), Point::new(-106.0, 39.0));
+        assert_close(geo.sample_point(1200, 1200), Point::new(-105.0, 38.0));
+        assert_close(
+            geo.sample_point(600, 1),
+            Point::new(-106.0 + 3.0 / 3600.0, 38.5),
+        );
+        assert_eq!(geo.idx_to_rowcol(1201 * 2 + 5), (2, 5));
+        assert_eq!(geo.rowcol_to_idx(2, 5), 1201 * 2 + 5);
+    }
+
+    #[test]
+    fn test_nearest_rowcol() {
+        let geo = Geometry::tile(&Point::new(-106, 38), 3601);
+        let bounds = geo.bounds();
+        assert_close(bounds.min().into(), Point::new(-106.0, 38.0));
+        assert_close(bounds.max().into(), Point::new(-105.0, 39.0));
+        let nudge: f64 = 0.4 / 3600.0;
+        for (row, col) in [(0, 0), (3600, 3600), (1, 3599), (1234, 567)] {
+            let point = geo.sample_point(row, col);
+            assert_eq!(geo.nearest_rowcol(&point), Some((row, col)));
+            let toward_center = Point::new(
+                point.x() + nudge.copysign(-105.5 - point.x()),
+                point.y() + nudge.copysign(38.5 - point.y()),
+            );
+            assert_eq!(geo.nearest_rowcol(&toward_center), Some((row, col)));
+        }
+        assert_eq!(geo.nearest_rowcol(&Point::new(-106.0001, 38.5)), None);
+        assert_eq!(geo.nearest_rowcol(&Point::new(-105.5, 39.0001)), None);
+
+        let area = Geometry::new(Point::new(10.0, 20.0), 0.25, Registration::Area, 4, 4);
+        let bounds = area.bounds();
+        assert_close(bounds.min().into(), Point::new(10.0, 19.0));
+        assert_close(bounds.max().into(), Point::new(11.0, 20.0));
+        assert_eq!(area.nearest_rowcol(&Point::new(10.1, 19.6)), Some((1, 0)));
+        assert_eq!(area.nearest_rowcol(&Point::new(10.9, 19.05)), Some((3, 3)));
+        assert_eq!(area.nearest_rowcol(&Point::new(10.0, 20.0)), Some((0, 0)));
+        assert_eq!(area.nearest_rowcol(&Point::new(11.01, 19.5)), None);
+    }
+
+    #[test]
+    fn test_window() {
+        let geo = Geometry::tile(&Point::new(0, 0), 5);
+        let window = Rect::new((0.1, 0.1), (0.2, 0.2));
+        assert_eq!(geo.window(&window), Some((3..5, 0..2)));
+        let window = Rect::new((-1.0, -1.0), (2.0, 2.0));
+        assert_eq!(geo.window(&window), Some((0..5, 0..5)));
+        let window = Rect::new((1.2, 0.0), (2.0, 1.0));
+        assert_eq!(geo.window(&window), None);
+
+        let area = Geometry::new(Point::new(10.0, 20.0), 0.25, Registration::Area, 4, 4);
+        let sub = area.subgrid(1..3, 2..4);
+        assert_eq!(sub.rows(), 2);
+        assert_eq!(sub.cols(), 2);
+        assert_close(sub.sample_point(0, 0), area.sample_point(1, 2));
+        assert_close(
+            sub.cell_rect(1, 1).min().into(),
+            area.cell_rect(2, 3).min().into(),
+        );
+    }
+
+    #[test]
+    fn test_point_registered_cell() {
+        let geo = Geometry::tile(&Point::new(-106, 38), 3601);
+        let half = 0.5 / 3600.0;
+        let rect = geo.cell_rect(0, 0);
+        assert_close(rect.min().into(), Point::new(-106.0 - half, 39.0 - half));
+        assert_close(rect.max().into(), Point::new(-106.0 + half, 39.0 + half));
+        let polygon = geo.cell_polygon(3600, 3600);
+        let corners: Vec<Point<f64>> = polygon.exterior().points().collect();
+        assert_eq!(corners.len(), 5);
+        assert_close(corners[0], Point::new(-105.0 - half, 38.0 - half));
+        assert_close(corners[2], Point::new(-105.0 + half, 38.0 + half));
+    }
+
+    #[test]
+    fn test_area_registered_cell() {
+        let geo = Geometry::new(Point::new(10.0, 20.0), 0.25, Registration::Area, 4, 4);
+        assert_close(geo.sample_point(0, 0), Point::new(10.125, 19.875));
+        let rect = geo.cell_rect(3, 1);
+        assert_close(rect.min().into(), Point::new(10.25, 19.0));
+        assert_close(rect.max().into(), Point::new(10.5, 19.25));
+    }
+}
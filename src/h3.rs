@@ -0,0 +1,408 @@
+//! Conversion of tiles to H3 cells.
+
+use crate::{mosaic::candidate_tiles, DEMBox, Interpolation, Mosaic, Result, Row, NASADEM};
+use geo_types::{Coord, Point, Rect};
+use hextree::{
+    compaction::EqCompactor,
+    h3ron::{self, collections::indexvec::IndexVec, H3Cell, ToCoordinate},
+    HexTreeMap,
+};
+use std::ops::Range;
+
+/// Elevations keyed by H3 cell, with runs of equal siblings compacted
+/// into their parent.
+pub type ElevationMap = HexTreeMap<i16, EqCompactor>;
+
+/// Counters from converting tiles to H3 cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct H3Stats {
+    /// Boxes with an elevation, which were converted.
+    pub boxes: usize,
+    /// Boxes without an elevation, which were left out.
+    pub voids: usize,
+    /// Cells whose center couldn't be sampled, which were left out.
+    ///
+    /// Only [`NASADEM::sample_hex_map`] samples cell centers.
+    pub unsampled: usize,
+    /// Cells inserted into the map, before compaction.
+    pub cells: usize,
+    /// Cells in the map after compaction.
+    pub compacted_cells: usize,
+}
+
+/// A box's elevation and the cells it covers, or `None` if it has no
+/// elevation.
+type BoxCells = Option<(i16, IndexVec<H3Cell>)>;
+
+/// Rows converted between insertions, which bounds how many cells are
+/// held in memory at once.
+const ROWS_PER_BATCH: usize = 64;
+
+impl NASADEM {
+    /// Converts this tile's elevation into H3 cells at `resolution`.
+    ///
+    /// Each cell whose center falls in a [`DEMBox::polygon`] gets that
+    /// box's elevation. Void boxes are left out, as are all boxes if no
+    /// elevation layer has been added. With the `rayon` feature, boxes
+    /// are converted on every core.
+    pub fn to_hex_map(&self, resolution: u8) -> Result<(ElevationMap, H3Stats)> {
+        let mut map = HexTreeMap::with_compactor(EqCompactor);
+        let mut stats = H3Stats::default();
+        self.insert_cells(&mut map, resolution, &mut stats, |_, _| true)?;
+        stats.compacted_cells = map.len();
+        Ok((map, stats))
+    }
+
+    /// Converts this tile's elevation into H3 cells at `resolution` by
+    /// sampling the elevation at each cell's center.
+    ///
+    /// Every cell whose center falls within [`Geometry::bounds`] is
+    /// sampled with `interp`, so unlike [`NASADEM::to_hex_map`] no cell
+    /// is missed when cells are smaller than the boxes, and each cell
+    /// costs one interpolation instead of each box costing a polygon
+    /// fill. Cells whose center can't be sampled, e.g. among voids, are
+    /// left out.
+    ///
+    /// [`Geometry::bounds`]: crate::Geometry::bounds
+    pub fn sample_hex_map(
+        &self,
+        resolution: u8,
+        interp: Interpolation,
+    ) -> Result<(ElevationMap, H3Stats)> {
+        let mut map = HexTreeMap::with_compactor(EqCompactor);
+        let mut stats = H3Stats::default();
+        insert_sampled_cells(&mut map, &mut stats, self, resolution, |point| {
+            self.sample(point, interp)
+        })?;
+        stats.compacted_cells = map.len();
+        Ok((map, stats))
+    }
+
+    /// Inserts the cells of the boxes for which `keep(row, col)` is
+    /// true.
+    fn insert_cells(
+        &self,
+        map: &mut ElevationMap,
+        resolution: u8,
+        stats: &mut H3Stats,
+        keep: impl Fn(usize, usize) -> bool + Sync + Send,
+    ) -> Result<()> {
+        for batch in self.row_batches() {
+            let converted = self.map_boxes(batch, |dem_box| {
+                let (row, col) = dem_box.rowcol();
+                if !keep(row, col) {
+                    return Ok(None);
+                }
+                polygon_cells(&dem_box, resolution).map(Some)
+            })?;
+            for converted in converted.into_iter().flatten() {
+                let Some((elevation, cells)) = converted else {
+                    stats.voids += 1;
+                    continue;
+                };
+                stats.boxes += 1;
+                for cell in &cells {
+                    map.insert(cell, elevation);
+                    stats.cells += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits this tile's rows into batches of [`ROWS_PER_BATCH`].
+    pub(crate) fn row_batches(&self) -> impl Iterator<Item = Range<usize>> {
+        let rows = self.geometry.rows();
+        (0..rows)
+            .step_by(ROWS_PER_BATCH)
+            .map(move |first| first..(first + ROWS_PER_BATCH).min(rows))
+    }
+
+    /// Applies `f` to every box in `rows`, in row-major order.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn map_boxes<T: Send>(
+        &self,
+        rows: Range<usize>,
+        f: impl Fn(DEMBox) -> Result<T> + Sync + Send,
+    ) -> Result<Vec<T>> {
+        use rayon::prelude::*;
+        rows.into_par_iter()
+            .flat_map_iter(|row| Row::new(self, row).boxes())
+            .map(f)
+            .collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    pub(crate) fn map_boxes<T: Send>(
+        &self,
+        rows: Range<usize>,
+        f: impl Fn(DEMBox) -> Result<T> + Sync + Send,
+    ) -> Result<Vec<T>> {
+        rows.flat_map(|row| Row::new(self, row).boxes())
+            .map(f)
+            .collect()
+    }
+}
+
+impl Mosaic {
+    /// Converts every tile's elevation into a single map of H3 cells
+    /// at `resolution`.
+    ///
+    /// See [`NASADEM::to_hex_map`]. Boxes on an edge shared by two
+    /// tiles are only converted and counted once.
+    pub fn to_hex_map(&self, resolution: u8) -> Result<(ElevationMap, H3Stats)> {
+        let mut map = HexTreeMap::with_compactor(EqCompactor);
+        let mut stats = H3Stats::default();
+        for dem in self.sorted_tiles() {
+            dem.insert_cells(&mut map, resolution, &mut stats, self.owned_samples(dem))?;
+        }
+        stats.compacted_cells = map.len();
+        Ok((map, stats))
+    }
+
+    /// Converts every tile's elevation into a single map of H3 cells
+    /// at `resolution` by sampling the mosaic at each cell's center.
+    ///
+    /// See [`NASADEM::sample_hex_map`]. Cells near tile edges are
+    /// interpolated across the edge.
+    pub fn sample_hex_map(
+        &self,
+        resolution: u8,
+        interp: Interpolation,
+    ) -> Result<(ElevationMap, H3Stats)> {
+        let mut map = HexTreeMap::with_compactor(EqCompactor);
+        let mut stats = H3Stats::default();
+        for dem in self.sorted_tiles() {
+            insert_sampled_cells(&mut map, &mut stats, dem, resolution, |point| {
+                self.sample(point, interp)
+            })?;
+        }
+        stats.compacted_cells = map.len();
+        Ok((map, stats))
+    }
+
+    /// Returns the tiles west to east, then south to north, so results
+    /// don't depend on hash order.
+    pub(crate) fn sorted_tiles(&self) -> Vec<&NASADEM> {
+        let mut tiles: Vec<&NASADEM> = self.tiles().collect();
+        tiles.sort_by_key(|dem| (dem.southwest_corner().y(), dem.southwest_corner().x()));
+        tiles
+    }
+
+    /// Returns whether `(row, col)` of `dem` should be used when
+    /// gathering samples from the whole mosaic.
+    ///
+    /// A sample on an edge or corner shared with neighbours belongs to
+    /// the first present tile in [`candidate_tiles`] order, so each is
+    /// gathered once however the tiles are arranged.
+    pub(crate) fn owned_samples<'a>(
+        &'a self,
+        dem: &NASADEM,
+    ) -> impl Fn(usize, usize) -> bool + Sync + Send + 'a {
+        let geometry = *dem.geometry();
+        let corner = *dem.southwest_corner();
+        // Edge samples can land a rounding error off the whole degree.
+        let snap = |v: f64| {
+            let whole = v.round();
+            if (v - whole).abs() < 1e-9 {
+                whole
+            } else {
+                v
+            }
+        };
+        move |row, col| {
+            let (x, y) = geometry.sample_point(row, col).x_y();
+            let (x, y) = (snap(x), snap(y));
+            if x.fract() != 0.0 && y.fract() != 0.0 {
+                return true;
+            }
+            candidate_tiles(Point::new(x, y))
+                .find(|candidate| self.get(candidate).is_some())
+                .is_none_or(|owner| owner == corner)
+        }
+    }
+}
+
+/// Inserts every cell centered within the bounds of `dem`, with
+/// elevations from `sample`.
+fn insert_sampled_cells(
+    map: &mut ElevationMap,
+    stats: &mut H3Stats,
+    dem: &NASADEM,
+    resolution: u8,
+    sample: impl Fn(Point<f64>) -> Option<f64> + Sync + Send,
+) -> Result<()> {
+    let geometry = dem.geometry();
+    let bounds = geometry.bounds();
+    // Fill the bounds one band at a time to bound memory use.
+    let band_height = geometry.spacing() * ROWS_PER_BATCH as f64;
+    let mut south = bounds.min().y;
+    while south < bounds.max().y {
+        let north = (south + band_height).min(bounds.max().y);
+        let band = Rect::new(
+            Coord {
+                x: bounds.min().x,
+                y: south,
+            },
+            Coord {
+                x: bounds.max().x,
+                y: north,
+            },
+        );
+        let cells = h3ron::polygon_to_cells(&band.to_polygon(), resolution)?;
+        let sampled = map_cells(&cells, |cell| {
+            let center = Point::from(cell.to_coordinate().ok()?);
+            sample(center).map(|z| z.round() as i16)
+        });
+        for (cell, elevation) in cells.iter().zip(sampled) {
+            match elevation {
+                Some(elevation) => {
+                    map.insert(cell, elevation);
+                    stats.cells += 1;
+                }
+                None => stats.unsampled += 1,
+            }
+        }
+        south = north;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+fn map_cells<T: Send>(
+    cells: &IndexVec<H3Cell>,
+    f: impl Fn(H3Cell) -> Option<T> + Sync + Send,
+) -> Vec<Option<T>> {
+    use rayon::prelude::*;
+    let cells: Vec<H3Cell> = cells.iter().collect();
+    cells.into_par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn map_cells<T: Send>(
+    cells: &IndexVec<H3Cell>,
+    f: impl Fn(H3Cell) -> Option<T> + Sync + Send,
+) -> Vec<Option<T>> {
+    cells.iter().map(f).collect()
+}
+
+fn polygon_cells(dem_box: &DEMBox, resolution: u8) -> Result<BoxCells> {
+    let Some(elevation) = dem_box.elevation() else {
+        return Ok(None);
+    };
+    let cells = h3ron::polygon_to_cells(&dem_box.polygon(), resolution)?;
+    Ok(Some((elevation, cells)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_support::test_tile, Error, VOID};
+    use geo_types::{Coord, Point};
+    use std::{
+        fs::File,
+        io::{BufReader, BufWriter},
+    };
+
+    /// A 3×3 tile whose samples are `10 * idx`, with a void center.
+    fn tile(southwest_corner: Point<i32>) -> NASADEM {
+        test_tile(3, southwest_corner, |row, col| match (row, col) {
+            (1, 1) => VOID,
+            _ => 10 * (3 * row + col) as i16,
+        })
+    }
+
+    fn cell_at(x: f64, y: f64) -> H3Cell {
+        H3Cell::from_coordinate(Coord { x, y }, 6).unwrap()
+    }
+
+    #[test]
+    fn test_tile_to_hex_map() {
+        let dem = tile(Point::new(10, 40));
+        let (map, stats) = dem.to_hex_map(6).unwrap();
+        assert_eq!(stats.boxes, 8);
+        assert_eq!(stats.voids, 1);
+        assert!(stats.cells > 0);
+        assert_eq!(stats.compacted_cells, map.len());
+        assert!(map.len() <= stats.cells);
+
+        assert_eq!(map.get(cell_at(10.0, 41.0)), Some(&0));
+        assert_eq!(map.get(cell_at(11.0, 41.0)), Some(&20));
+        assert_eq!(map.get(cell_at(10.5, 40.0)), Some(&70));
+        assert_eq!(map.get(cell_at(10.5, 40.5)), None);
+
+        assert!(matches!(dem.to_hex_map(16), Err(Error::H3(_))));
+    }
+
+    #[test]
+    fn test_mosaic_to_hex_map() {
+        let mosaic: Mosaic = [Point::new(10, 40), Point::new(11, 40)]
+            .into_iter()
+            .map(tile)
+            .collect();
+        let (map, stats) = mosaic.to_hex_map(6).unwrap();
+        // The shared column is converted once.
+        assert_eq!(stats.boxes, 13);
+        assert_eq!(map.get(cell_at(10.0, 41.0)), Some(&0));
+        assert_eq!(map.get(cell_at(12.0, 40.0)), Some(&80));
+    }
+
+    #[test]
+    fn test_sample_hex_map() {
+        // A plane rising 100 m per degree of longitude.
+        let dem = test_tile(5, Point::new(10, 40), |_, col| 25 * col as i16);
+
+        let (map, stats) = dem.sample_hex_map(5, Interpolation::Bilinear).unwrap();
+        assert!(stats.cells > 0);
+        assert_eq!(stats.unsampled, 0);
+        assert_eq!(stats.boxes, 0);
+        for (cell, &elevation) in map.iter() {
+            let center = cell.to_coordinate().unwrap();
+            assert!((10.0..=11.0).contains(&center.x));
+            assert_eq!(elevation, (100.0 * (center.x - 10.0_f64)).round() as i16);
+        }
+        // Every cell centered in the tile is sampled.
+        let bounds = dem.geometry().bounds().to_polygon();
+        let expected = h3ron::polygon_to_cells(&bounds, 5).unwrap().iter().count();
+        assert_eq!(stats.cells, expected);
+
+        let (voids, stats) = tile(Point::new(10, 40))
+            .sample_hex_map(5, Interpolation::Nearest)
+            .unwrap();
+        assert!(stats.unsampled > 0);
+        assert!(voids.get(cell_at(10.5, 40.5)).is_none());
+
+        let mosaic: Mosaic = [dem].into_iter().collect();
+        let (mosaic_map, _) = mosaic.sample_hex_map(5, Interpolation::Bilinear).unwrap();
+        assert_eq!(mosaic_map.len(), map.len());
+    }
+
+    #[test]
+    fn test_local_tile() {
+        let elevation_src = BufReader::new(
+            File::open(format!(
+                "{}/local/NASADEM_HGT_n38w106/n38w106.hgt",
+                std::env!("CARGO_MANIFEST_DIR")
+            ))
+            .unwrap(),
+        );
+
+        let mut dem = NASADEM::new(Point::new(-106, 38));
+        dem.add_elevation(elevation_src).unwrap();
+
+        let (elev_map, stats) = dem.to_hex_map(14).unwrap();
+
+        let out = BufWriter::new(
+            File::create(format!(
+                "{}/local/NASADEM_HGT_n38w106/n38w106.res14.bincode.hexmap",
+                std::env!("CARGO_MANIFEST_DIR")
+            ))
+            .unwrap(),
+        );
+
+        bincode::serialize_into(out, &elev_map).unwrap();
+
+        println!("{stats:?}");
+        assert!(elev_map.len() < stats.cells);
+    }
+}
@@ -0,0 +1,135 @@
+//! Shaded relief.
+
+use crate::{terrain::gradient, Raster, NASADEM};
+
+/// Lighting for [`NASADEM::hillshade`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hillshade {
+    /// Direction of the sun in degrees clockwise from north.
+    pub azimuth: f64,
+    /// Angle of the sun above the horizon in degrees.
+    pub altitude: f64,
+    /// Vertical exaggeration applied to elevations.
+    pub z_factor: f64,
+    /// Blend light from the west, northwest, north and southwest,
+    /// weighting each by how squarely it hits the slope, instead of
+    /// using `azimuth`.
+    pub multidirectional: bool,
+}
+
+impl Default for Hillshade {
+    /// Light from the northwest, 45° above the horizon.
+    fn default() -> Self {
+        Self {
+            azimuth: 315.0,
+            altitude: 45.0,
+            z_factor: 1.0,
+            multidirectional: false,
+        }
+    }
+}
+
+/// Azimuths blended by [`Hillshade::multidirectional`].
+const MULTIDIRECTIONAL_AZIMUTHS: [f64; 4] = [225.0, 270.0, 315.0, 360.0];
+
+impl NASADEM {
+    /// Returns the brightness of every sample under `lighting`.
+    ///
+    /// Fully shadowed samples are 1 and fully lit ones 255; void
+    /// samples are 0. Gradients are computed as for
+    /// [`NASADEM::slope_raster`].
+    pub fn hillshade(&self, lighting: &Hillshade) -> Raster<u8> {
+        Raster::from_fn(self.geometry, |row, col| {
+            let Some((dz_dx, dz_dy)) = gradient(self, row, col) else {
+                return 0;
+            };
+            let (dz_dx, dz_dy) = (lighting.z_factor * dz_dx, lighting.z_factor * dz_dy);
+            let shade = if lighting.multidirectional {
+                let aspect = (-dz_dx).atan2(-dz_dy);
+                let weighted: f64 = MULTIDIRECTIONAL_AZIMUTHS
+                    .iter()
+                    .map(|&azimuth| {
+                        let weight = (aspect - azimuth.to_radians()).sin().powi(2);
+                        weight * illumination(dz_dx, dz_dy, azimuth, lighting.altitude)
+                    })
+                    .sum();
+                // The weights of the four azimuths always sum to 2.
+                weighted / 2.0
+            } else {
+                illumination(dz_dx, dz_dy, lighting.azimuth, lighting.altitude)
+            };
+            1 + (254.0 * shade).round() as u8
+        })
+    }
+}
+
+/// Returns the cosine of the angle between the surface normal and the
+/// sun, clamped to 0 for surfaces facing away from it.
+fn illumination(dz_dx: f64, dz_dy: f64, azimuth: f64, altitude: f64) -> f64 {
+    let (azimuth, altitude) = (azimuth.to_radians(), altitude.to_radians());
+    let sun = [
+        azimuth.sin() * altitude.cos(),
+        azimuth.cos() * altitude.cos(),
+        altitude.sin(),
+    ];
+    let normal = [-dz_dx, -dz_dy, 1.0];
+    let length = (dz_dx * dz_dx + dz_dy * dz_dy + 1.0).sqrt();
+    let cos: f64 = sun.iter().zip(normal).map(|(s, n)| s * n).sum();
+    (cos / length).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_support::test_tile, VOID};
+    use geo_types::Point;
+
+    #[test]
+    fn test_flat() {
+        let dem = test_tile(5, Point::new(0, 0), |row, col| match (row, col) {
+            (2, 2) => VOID,
+            _ => 0,
+        });
+        let shade = dem.hillshade(&Hillshade::default());
+        // Flat ground is lit at the sun's altitude.
+        let expected = 1 + (254.0 * 45_f64.to_radians().sin()).round() as u8;
+        assert_eq!(shade.get(0, 0), expected);
+        assert_eq!(shade.get(2, 2), 0);
+
+        let overhead = Hillshade {
+            altitude: 90.0,
+            ..Hillshade::default()
+        };
+        assert_eq!(dem.hillshade(&overhead).get(1, 1), 255);
+    }
+
+    #[test]
+    fn test_sun_direction() {
+        // A west-facing slope of about 16°.
+        let dem = test_tile(5, Point::new(0, 0), |_, col| 8_000 * col as i16);
+        let lit = |azimuth| {
+            let lighting = Hillshade {
+                azimuth,
+                ..Hillshade::default()
+            };
+            dem.hillshade(&lighting).get(1, 1)
+        };
+        assert!(lit(270.0) > lit(315.0));
+        assert!(lit(315.0) > lit(0.0));
+        assert!(lit(0.0) > lit(90.0));
+
+        let exaggerated = Hillshade {
+            azimuth: 270.0,
+            z_factor: 2.0,
+            ..Hillshade::default()
+        };
+        assert_ne!(dem.hillshade(&exaggerated).get(1, 1), lit(270.0));
+
+        let multi = Hillshade {
+            multidirectional: true,
+            ..Hillshade::default()
+        };
+        let shade = dem.hillshade(&multi).get(1, 1);
+        assert!(shade > lit(0.0) && shade <= lit(270.0), "{shade}");
+    }
+}
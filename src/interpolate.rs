@@ -0,0 +1,157 @@
+//! Sub-sample elevation interpolation.
+
+/// How to estimate elevation between samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Value of the nearest sample.
+    Nearest,
+    /// Weighted average of the surrounding 2×2 samples.
+    ///
+    /// Void samples are left out and the remaining weights
+    /// renormalized.
+    #[default]
+    Bilinear,
+    /// Catmull-Rom spline through the surrounding 4×4 samples.
+    ///
+    /// Falls back to [`Interpolation::Bilinear`] wherever one of the
+    /// 16 samples is void or unavailable.
+    Bicubic,
+}
+
+/// Interpolates the grid at fractional `(row, col)`.
+///
+/// `fetch` returns the sample at an integer position, or `None` if it
+/// is void or outside the grid. Samples whose weight is zero are never
+/// fetched, so a position exactly on a grid line only depends on
+/// samples along that line. That is what makes results on a tile's
+/// edge agree with its neighbour, which shares the edge samples.
+pub(crate) fn interpolate(
+    interp: Interpolation,
+    row: f64,
+    col: f64,
+    fetch: impl Fn(isize, isize) -> Option<f64>,
+) -> Option<f64> {
+    match interp {
+        Interpolation::Nearest => fetch(row.round() as isize, col.round() as isize),
+        Interpolation::Bilinear => bilinear(row, col, &fetch),
+        Interpolation::Bicubic => bicubic(row, col, &fetch).or_else(|| bilinear(row, col, &fetch)),
+    }
+}
+
+fn bilinear(row: f64, col: f64, fetch: &impl Fn(isize, isize) -> Option<f64>) -> Option<f64> {
+    let (r0, c0) = (row.floor(), col.floor());
+    let (tr, tc) = (row - r0, col - c0);
+    let (r0, c0) = (r0 as isize, c0 as isize);
+    let mut sum = 0.0;
+    let mut total_weight = 0.0;
+    for (dr, wr) in [(0, 1.0 - tr), (1, tr)] {
+        for (dc, wc) in [(0, 1.0 - tc), (1, tc)] {
+            let weight = wr * wc;
+            if weight == 0.0 {
+                continue;
+            }
+            if let Some(sample) = fetch(r0 + dr, c0 + dc) {
+                sum += weight * sample;
+                total_weight += weight;
+            }
+        }
+    }
+    (total_weight > 0.0).then(|| sum / total_weight)
+}
+
+/// Returns `None` if any sample with a non-zero weight is missing.
+fn bicubic(row: f64, col: f64, fetch: &impl Fn(isize, isize) -> Option<f64>) -> Option<f64> {
+    let (r0, c0) = (row.floor(), col.floor());
+    let (wrs, wcs) = (catmull_rom_weights(row - r0), catmull_rom_weights(col - c0));
+    let (r0, c0) = (r0 as isize, c0 as isize);
+    let mut sum = 0.0;
+    for (dr, wr) in (-1..=2).zip(wrs) {
+        for (dc, wc) in (-1..=2).zip(wcs) {
+            let weight = wr * wc;
+            if weight != 0.0 {
+                sum += weight * fetch(r0 + dr, c0 + dc)?;
+            }
+        }
+    }
+    Some(sum)
+}
+
+/// Weights of the four samples around fractional offset `t ∈ [0, 1)`.
+fn catmull_rom_weights(t: f64) -> [f64; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    [
+        (-t3 + 2.0 * t2 - t) / 2.0,
+        (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+        (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+        (t3 - t2) / 2.0,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 5×5 plane, `z = 10 * row + col`, with a void at (2, 2).
+    fn plane(row: isize, col: isize) -> Option<f64> {
+        ((0..5).contains(&row) && (0..5).contains(&col) && (row, col) != (2, 2))
+            .then(|| (10 * row + col) as f64)
+    }
+
+    #[test]
+    fn test_reproduces_plane() {
+        let fetch = |r, c| plane(r, c).or(Some((10 * r + c) as f64));
+        for interp in [Interpolation::Bilinear, Interpolation::Bicubic] {
+            for (row, col) in [(1.25, 1.5), (2.0, 2.5), (0.0, 0.0), (4.0, 4.0), (3.9, 0.1)] {
+                let z = interpolate(interp, row, col, fetch).unwrap();
+                assert!(
+                    (z - (10.0 * row + col)).abs() < 1e-9,
+                    "{interp:?} {row} {col}"
+                );
+            }
+        }
+        assert_eq!(
+            interpolate(Interpolation::Nearest, 1.4, 2.6, fetch),
+            Some(13.0)
+        );
+    }
+
+    #[test]
+    fn test_voids() {
+        for interp in [
+            Interpolation::Nearest,
+            Interpolation::Bilinear,
+            Interpolation::Bicubic,
+        ] {
+            assert_eq!(interpolate(interp, 2.0, 2.0, plane), None, "{interp:?}");
+        }
+        // Bilinear renormalizes around the void; bicubic falls back.
+        let expected = (21.0 * 0.25 + 31.0 * 0.25 + 32.0 * 0.25) / 0.75;
+        for interp in [Interpolation::Bilinear, Interpolation::Bicubic] {
+            let z = interpolate(interp, 2.5, 1.5, plane).unwrap();
+            assert!((z - expected).abs() < 1e-9, "{interp:?}");
+        }
+    }
+
+    #[test]
+    fn test_edges_only_use_edge_samples() {
+        // Nothing beyond row/col 4 exists, but the last row and column
+        // still interpolate without falling back or renormalizing.
+        let fetch = |r, c| {
+            assert!(
+                (0..5).contains(&r) && (0..5).contains(&c),
+                "fetched {r},{c}"
+            );
+            plane(r, c)
+        };
+        assert_eq!(
+            interpolate(Interpolation::Bilinear, 4.0, 3.5, fetch),
+            Some(43.5)
+        );
+        let curved = |r: isize, c: isize| {
+            assert!((0..5).contains(&c), "fetched {r},{c}");
+            Some((r * r + c * c) as f64)
+        };
+        let z = interpolate(Interpolation::Bicubic, 2.5, 4.0, curved).unwrap();
+        assert!((z - (6.25 + 16.0)).abs() < 1e-9);
+    }
+}
@@ -1,84 +1,414 @@
 //! Parsers for NASA Digital Elevation Model.
 
-use byteorder::{BigEndian as BE, ReadBytesExt};
-use geo_types::{LineString, Point, Polygon};
-use std::io::{Error as IoError, Read};
+#[cfg(feature = "h3")]
+mod aggregate;
+#[cfg(feature = "zip")]
+mod archive;
+mod cache;
+mod elevation;
+mod error;
+mod geodesy;
+mod geometry;
+#[cfg(feature = "h3")]
+mod h3;
+mod hillshade;
+mod interpolate;
+mod lines;
+mod metrics;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod mosaic;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod profile;
+#[cfg(feature = "h3")]
+mod pyramid;
+mod raster;
+mod source;
+mod terrain;
+#[cfg(test)]
+mod test_support;
+mod tile_name;
+mod water;
+
+use crate::elevation::Elevation;
+#[cfg(feature = "h3")]
+pub use crate::{
+    aggregate::{Aggregation, CellSummary, CellValue},
+    h3::{ElevationMap, H3Stats},
+    pyramid::{CellRange, HexPyramid, PyramidBuilder},
+};
+pub use crate::{
+    cache::{CacheStats, TileCache},
+    error::{Error, Result},
+    geometry::{Geometry, Registration},
+    hillshade::Hillshade,
+    interpolate::Interpolation,
+    lines::{Column, Row},
+    metrics::TerrainMetrics,
+    mosaic::Mosaic,
+    profile::ProfilePoint,
+    raster::Raster,
+    source::Source,
+    terrain::SlopeUnits,
+    tile_name::{parse_tile_name, tile_name},
+    water::WaterMask,
+};
+use byteorder::{BigEndian as BE, ByteOrder};
+use geo_types::{Point, Polygon, Rect};
+use std::{
+    io::{self, Read},
+    path::Path,
+};
 
 type DEMMatrix<T> = Vec<T>;
 
+/// Samples per side of a 1 arc-second (SRTM1, NASADEM) tile.
+pub const SRTM1_SIZE: usize = 3601;
+
+/// Samples per side of a 3 arc-second (SRTM3) tile.
+pub const SRTM3_SIZE: usize = 1201;
+
+/// Sample value HGT files use to mark cells with no elevation data.
+pub const VOID: i16 = i16::MIN;
+
 #[derive(Debug)]
 pub struct NASADEM {
     southwest_corner: Point<i32>,
-    elevation: Option<DEMMatrix<u16>>,
-    water: Option<DEMMatrix<bool>>,
+    geometry: Geometry,
+    /// Whether the caller chose this tile's size rather than leaving
+    /// it to be detected from the first layer.
+    size_is_explicit: bool,
+    elevation: Option<Elevation>,
+    water: Option<WaterMask>,
+    source: Option<DEMMatrix<u8>>,
+    slope: Option<Raster<f32>>,
+    aspect: Option<Raster<f32>>,
 }
 
 impl NASADEM {
+    /// Returns an empty tile whose size is detected from the byte
+    /// length of the first layer added to it.
     pub fn new(southwest_corner: Point<i32>) -> Self {
+        let mut dem = Self::with_size(southwest_corner, SRTM1_SIZE);
+        dem.size_is_explicit = false;
+        dem
+    }
+
+    /// Returns an empty tile whose southwest corner is parsed from a
+    /// tile file name such as `NASADEM_HGT_n38w106/n38w106.hgt`.
+    ///
+    /// See [`parse_tile_name`] for the accepted spellings.
+    pub fn from_tile_name(path: impl AsRef<Path>) -> Result<Self> {
+        parse_tile_name(path).map(Self::new)
+    }
+
+    /// Returns an empty tile with `size` samples per side.
+    ///
+    /// Layers added to it must have exactly `size * size` samples.
+    pub fn with_size(southwest_corner: Point<i32>, size: usize) -> Self {
+        assert!(size >= 2, "a tile needs at least two samples per side");
         Self {
+            geometry: Geometry::tile(&southwest_corner, size),
             southwest_corner,
+            size_is_explicit: true,
             elevation: None,
             water: None,
+            source: None,
+            slope: None,
+            aspect: None,
         }
     }
 
-    pub fn add_elevation(&mut self, mut src: impl Read) -> Result<&mut Self, IoError> {
-        let mut elev_samples = Vec::with_capacity(3601 * 3601);
-        let mut idx = 0_usize;
-        for y in (0..3601).rev() {
-            let lat_b = self.southwest_corner.y() as f64 + y as f64 / 3601.0;
-            let lat_t = lat_b + 1.0 / 3601.0;
-            debug_assert!(lat_t <= (self.southwest_corner.y() + 1) as f64);
-            debug_assert!(lat_b < (self.southwest_corner.y() + 1) as f64);
-            debug_assert!(lat_b >= self.southwest_corner.y() as f64);
-            debug_assert!(lat_t > self.southwest_corner.y() as f64);
-            for x in 0..3601 {
-                let lon_l = self.southwest_corner.x() as f64 + x as f64 / 3601.0;
-                let lon_r = lon_l + 1.0 / 3601.0;
-                debug_assert!(lon_r <= (self.southwest_corner.x() + 1) as f64);
-                debug_assert!(lon_l < (self.southwest_corner.x() + 1) as f64);
-                debug_assert!(lon_r > self.southwest_corner.x() as f64);
-                debug_assert!(lon_l >= self.southwest_corner.x() as f64);
-                let sample = src.read_u16::<BE>()?;
-                elev_samples.push(sample);
-                debug_assert_eq!(
-                    (idx, idx_to_pont(&self.southwest_corner, idx)),
-                    (idx, Point::new(lon_l, lat_b))
-                );
-                idx += 1;
-            }
-        }
-        debug_assert_eq!(elev_samples.len(), 3601 * 3601);
-        self.elevation = Some(elev_samples);
+    pub fn add_elevation(&mut self, src: impl Read) -> Result<&mut Self> {
+        let elev_samples = self.read_elevation(src)?;
+        let void_count = elev_samples.iter().filter(|&&s| s == VOID).count();
+        debug_assert_eq!(elev_samples.len(), self.geometry.len());
+        self.set_elevation(Elevation::Owned {
+            samples: elev_samples,
+            void_count,
+        });
         Ok(self)
     }
 
-    pub fn add_water(&mut self, mut src: impl Read) -> Result<&mut Self, IoError> {
-        let mut water_samples = Vec::with_capacity(3601 * 3601);
-        for _i in 0..3601 {
-            for _j in 0..3601 {
-                let sample = src.read_u8()?;
-                debug_assert!(sample == 0 || sample == 255);
-                water_samples.push(sample == 255);
+    /// Replaces the elevation layer, dropping the layers derived from
+    /// it.
+    fn set_elevation(&mut self, elevation: Elevation) {
+        self.elevation = Some(elevation);
+        self.slope = None;
+        self.aspect = None;
+    }
+
+    pub fn add_water(&mut self, src: impl Read) -> Result<&mut Self> {
+        let buf = self.read_layer(src, 1)?;
+        let water_mask = WaterMask::from_swb(&buf, self.geometry.rows(), self.geometry.cols())?;
+        self.water = Some(water_mask);
+        Ok(self)
+    }
+
+    /// Adds the source layer from a NASADEM `.num` file, recording
+    /// where each sample's elevation came from.
+    pub fn add_source(&mut self, src: impl Read) -> Result<&mut Self> {
+        let source_samples = self.read_layer(src, 1)?;
+        debug_assert_eq!(source_samples.len(), self.geometry.len());
+        self.source = Some(source_samples);
+        Ok(self)
+    }
+
+    /// Reads an entire layer of `sample_bytes`-wide samples and checks
+    /// its length against this tile.
+    fn read_layer(&mut self, mut src: impl Read, sample_bytes: usize) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        if self.size_is_known() {
+            buf.reserve_exact(self.geometry.len() * sample_bytes);
+        }
+        src.read_to_end(&mut buf)?;
+        self.check_layer_len(buf.len(), sample_bytes)?;
+        Ok(buf)
+    }
+
+    /// Like [`NASADEM::read_layer`], but decodes big-endian samples as
+    /// they arrive so the raw bytes are never held all at once.
+    fn read_elevation(&mut self, mut src: impl Read) -> Result<Vec<i16>> {
+        let mut samples = Vec::new();
+        if self.size_is_known() {
+            samples.reserve_exact(self.geometry.len());
+        }
+        let mut buf = [0_u8; 64 * 1024];
+        // Bytes at the start of `buf` left over from an odd-length read.
+        let mut pending = 0;
+        loop {
+            let n = match src.read(&mut buf[pending..]) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let filled = pending + n;
+            let whole = filled / 2;
+            let start = samples.len();
+            samples.resize(start + whole, 0);
+            BE::read_i16_into(&buf[..2 * whole], &mut samples[start..]);
+            buf.copy_within(2 * whole..filled, 0);
+            pending = filled - 2 * whole;
+        }
+        self.check_layer_len(2 * samples.len() + pending, 2)?;
+        Ok(samples)
+    }
+
+    /// Returns `true` if this tile's size is fixed, either explicitly
+    /// or by a layer already added.
+    fn size_is_known(&self) -> bool {
+        self.size_is_explicit
+            || self.elevation.is_some()
+            || self.water.is_some()
+            || self.source.is_some()
+    }
+
+    /// Checks that a layer of `len` bytes fits this tile, detecting
+    /// the tile's size from it if that isn't pinned yet.
+    fn check_layer_len(&mut self, len: usize, sample_bytes: usize) -> Result<()> {
+        if self.size_is_known() {
+            let (expected, got) = (self.geometry.len() * sample_bytes, len);
+            if got < expected {
+                return Err(Error::Truncated { expected, got });
+            }
+            if got > expected {
+                return Err(Error::Oversized { expected, got });
+            }
+        } else {
+            let size = ((len / sample_bytes) as f64).sqrt().round() as usize;
+            if size < 2 || size * size * sample_bytes != len {
+                // Most likely a cut-off or padded download, so report it
+                // against the closest standard tile.
+                let expected = [SRTM1_SIZE, SRTM3_SIZE]
+                    .map(|size| size * size * sample_bytes)
+                    .into_iter()
+                    .min_by_key(|expected| expected.abs_diff(len))
+                    .unwrap();
+                return Err(if len < expected {
+                    Error::Truncated { expected, got: len }
+                } else {
+                    Error::Oversized { expected, got: len }
+                });
             }
+            self.geometry = Geometry::tile(&self.southwest_corner, size);
         }
-        debug_assert_eq!(water_samples.len(), 3601 * 3601);
-        self.water = Some(water_samples);
-        Ok(self)
+        Ok(())
+    }
+
+    pub fn southwest_corner(&self) -> &Point<i32> {
+        &self.southwest_corner
+    }
+
+    /// Returns the geometry mapping this tile's samples to
+    /// coordinates.
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    /// Returns the number of bytes used by this tile's layers.
+    pub fn heap_size(&self) -> usize {
+        self.elevation.as_ref().map_or(0, Elevation::heap_size)
+            + self.water.as_ref().map_or(0, WaterMask::heap_size)
+            + self.source.as_ref().map_or(0, |s| s.len())
+            + self.slope.as_ref().map_or(0, Raster::heap_size)
+            + self.aspect.as_ref().map_or(0, Raster::heap_size)
+    }
+
+    /// Returns the water layer, if one has been added.
+    pub fn water_mask(&self) -> Option<&WaterMask> {
+        self.water.as_ref()
+    }
+
+    /// Returns the number of void samples in the elevation layer.
+    ///
+    /// Returns `None` if no elevation layer has been added.
+    pub fn void_count(&self) -> Option<usize> {
+        self.elevation.as_ref().map(Elevation::void_count)
+    }
+
+    /// Returns the elevation of the sample nearest to `point`.
+    ///
+    /// Returns `None` if `point` is outside this tile, the nearest
+    /// sample is void, or no elevation layer has been added.
+    pub fn elevation_at(&self, point: Point<f64>) -> Option<i16> {
+        let (row, col) = self.geometry.nearest_rowcol(&point)?;
+        self.elevation_sample(row, col)
+    }
+
+    /// Returns whether the sample nearest to `point` is water.
+    ///
+    /// Returns `None` if `point` is outside this tile or no water
+    /// layer has been added.
+    pub fn water_at(&self, point: Point<f64>) -> Option<bool> {
+        let (row, col) = self.geometry.nearest_rowcol(&point)?;
+        Some(self.water.as_ref()?.get(row, col))
+    }
+
+    /// Returns the elevation at `point`, interpolated from the samples
+    /// around it.
+    ///
+    /// Returns `None` if `point` is outside this tile or too close to
+    /// void samples to estimate; see [`Interpolation`] for how each
+    /// method treats voids.
+    pub fn sample(&self, point: Point<f64>, interp: Interpolation) -> Option<f64> {
+        self.geometry.nearest_rowcol(&point)?;
+        let (row, col) = self.geometry.fractional_rowcol(&point);
+        interpolate::interpolate(interp, row, col, |row, col| {
+            let (row, col) = self.geometry.checked_rowcol(row, col)?;
+            self.elevation_sample(row, col).map(f64::from)
+        })
+    }
+
+    /// Returns the non-void elevation at `(row, col)`.
+    fn elevation_sample(&self, row: usize, col: usize) -> Option<i16> {
+        let elevation = self.elevation.as_ref()?;
+        Some(elevation.get(self.geometry.rowcol_to_idx(row, col))).filter(|&e| e != VOID)
     }
 
     pub fn iter(&'_ self) -> impl Iterator<Item = DEMBox> + '_ {
         Iter { dem: self, idx: 0 }
     }
+
+    /// Returns an iterator over this tile's rows, north to south.
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = Row<'_>> + '_ {
+        (0..self.geometry.rows()).map(move |row| Row::new(self, row))
+    }
+
+    /// Returns an iterator over this tile's columns, west to east.
+    pub fn cols(&self) -> impl ExactSizeIterator<Item = Column<'_>> + '_ {
+        (0..self.geometry.cols()).map(move |col| Column::new(self, col))
+    }
+
+    /// Returns an iterator over the boxes that intersect `window`,
+    /// without visiting the rest of the grid.
+    pub fn iter_window(&'_ self, window: Rect<f64>) -> impl Iterator<Item = DEMBox> + '_ {
+        let (rows, cols) = self.geometry.window(&window).unwrap_or((0..0, 0..0));
+        rows.flat_map(move |row| cols.clone().map(move |col| self.dem_box(row, col)))
+    }
+
+    /// Returns a copy of the part of this tile that intersects
+    /// `window`, or `None` if they don't overlap.
+    ///
+    /// The copy is georeferenced by its own [`Geometry`], so every
+    /// query and iterator works on it the same as on a full tile; its
+    /// [`NASADEM::southwest_corner`] still names the tile it came
+    /// from.
+    pub fn crop(&self, window: Rect<f64>) -> Option<NASADEM> {
+        let (rows, cols) = self.geometry.window(&window)?;
+        let geometry = self.geometry.subgrid(rows.clone(), cols.clone());
+        let indices = || {
+            let cols = cols.clone();
+            rows.clone().flat_map(move |row| {
+                cols.clone()
+                    .map(move |col| self.geometry.rowcol_to_idx(row, col))
+            })
+        };
+        let elevation = self.elevation.as_ref().map(|elevation| {
+            let samples: Vec<i16> = indices().map(|idx| elevation.get(idx)).collect();
+            let void_count = samples.iter().filter(|&&s| s == VOID).count();
+            Elevation::Owned {
+                samples,
+                void_count,
+            }
+        });
+        let water = self.water.as_ref().map(|water| {
+            let mut mask = WaterMask::new(geometry.rows(), geometry.cols());
+            for (row, src_row) in rows.clone().enumerate() {
+                for (col, src_col) in cols.clone().enumerate() {
+                    mask.set(row, col, water.get(src_row, src_col));
+                }
+            }
+            mask
+        });
+        let source = self
+            .source
+            .as_ref()
+            .map(|source| indices().map(|idx| source[idx]).collect());
+        let crop_raster = |raster: &Raster<f32>| raster.subgrid(rows.clone(), cols.clone());
+        Some(NASADEM {
+            southwest_corner: self.southwest_corner,
+            geometry,
+            size_is_explicit: true,
+            elevation,
+            water,
+            source,
+            slope: self.slope.as_ref().map(crop_raster),
+            aspect: self.aspect.as_ref().map(crop_raster),
+        })
+    }
+
+    fn dem_box(&self, row: usize, col: usize) -> DEMBox {
+        let idx = self.geometry.rowcol_to_idx(row, col);
+        let elevation = self.elevation.as_ref().map(|e| e.get(idx));
+        DEMBox {
+            geometry: self.geometry,
+            southwest_corner: self.geometry.cell_rect(row, col).min().into(),
+            row,
+            col,
+            elevation: elevation.filter(|&e| e != VOID),
+            is_void: elevation.map(|e| e == VOID),
+            is_water: self.water.as_ref().map(|w| w.get(row, col)),
+            source: self.source.as_ref().map(|s| Source::from(s[idx])),
+            slope: self.slope.as_ref().map(|s| s.data()[idx]),
+            aspect: self.aspect.as_ref().map(|a| a.data()[idx]),
+        }
+    }
 }
 
+/// Returns the location of sample `idx` in a 1 arc-second tile whose
+/// southwest corner is `sw_corner`.
+///
+/// HGT samples are pixel-is-point, so sample 0 is exactly the tile's
+/// northwest corner. This assumes [`SRTM1_SIZE`] samples per side; use
+/// [`Geometry::idx_to_rowcol`] and [`Geometry::sample_point`] instead, which
+/// know the tile's actual size.
+#[deprecated(note = "use `Geometry::idx_to_rowcol` and `Geometry::sample_point`")]
 pub fn idx_to_pont(sw_corner: &Point<i32>, idx: usize) -> Point<f64> {
-    debug_assert!(idx < 3601 * 3601);
-    let y = 3600 - (idx / 3601);
-    let lat_south = sw_corner.y() as f64 + y as f64 / 3601.0;
-    let x = idx % 3601;
-    let lon_west = sw_corner.x() as f64 + x as f64 / 3601.0;
-    Point::new(lon_west, lat_south)
+    let geometry = Geometry::tile(sw_corner, SRTM1_SIZE);
+    let (row, col) = geometry.idx_to_rowcol(idx);
+    geometry.sample_point(row, col)
 }
 
 struct Iter<'a> {
@@ -90,16 +420,10 @@ impl<'a> Iterator for Iter<'a> {
     type Item = DEMBox;
 
     fn next(&mut self) -> Option<DEMBox> {
-        if self.idx < 3601 * 3601 {
-            let southwest_corner = idx_to_pont(&self.dem.southwest_corner, self.idx);
-            let elevation = self.dem.elevation.as_ref().map(|e| e[self.idx]);
-            let is_water = self.dem.water.as_ref().map(|w| w[self.idx]);
+        if self.idx < self.dem.geometry.len() {
+            let (row, col) = self.dem.geometry.idx_to_rowcol(self.idx);
             self.idx += 1;
-            Some(DEMBox {
-                southwest_corner,
-                elevation,
-                is_water,
-            })
+            Some(self.dem.dem_box(row, col))
         } else {
             None
         }
@@ -107,50 +431,85 @@ impl<'a> Iterator for Iter<'a> {
 }
 
 pub struct DEMBox {
+    geometry: Geometry,
     southwest_corner: Point<f64>,
-    elevation: Option<u16>,
+    row: usize,
+    col: usize,
+    elevation: Option<i16>,
+    is_void: Option<bool>,
     is_water: Option<bool>,
+    source: Option<Source>,
+    slope: Option<f32>,
+    aspect: Option<f32>,
 }
 
 impl DEMBox {
+    /// Returns the area this sample represents.
     pub fn polygon(&self) -> Polygon {
-        let lat_south = self.southwest_corner.y();
-        let lat_north = lat_south + 1.0 / 3601.0;
-        let lon_west = self.southwest_corner.x();
-        let lon_east = lon_west + (1.0 / 3601.0);
-        Polygon::new(
-            LineString::from(vec![
-                (lon_west, lat_south),
-                (lon_east, lat_south),
-                (lon_east, lat_north),
-                (lon_west, lat_north),
-                (lon_west, lat_south),
-            ]),
-            Vec::new(),
-        )
+        self.geometry.cell_polygon(self.row, self.col)
     }
 
+    /// Returns the southwest corner of the area this sample represents.
     pub fn southwest_corner(&self) -> &Point {
         &self.southwest_corner
     }
 
-    pub fn elevation(&self) -> Option<u16> {
+    /// Returns the location of the sample itself.
+    pub fn center(&self) -> Point {
+        self.geometry.sample_point(self.row, self.col)
+    }
+
+    /// Returns this box's position in its tile's grid.
+    pub fn rowcol(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Returns this box's elevation in meters.
+    ///
+    /// Returns `None` if the cell is void or no elevation layer has
+    /// been added; use [`DEMBox::is_void`] to tell the two apart.
+    pub fn elevation(&self) -> Option<i16> {
         self.elevation
     }
 
+    /// Returns `Some(true)` if the source HGT marked this cell as void.
+    pub fn is_void(&self) -> Option<bool> {
+        self.is_void
+    }
+
     pub fn is_water(&self) -> Option<bool> {
         self.is_water
     }
+
+    /// Returns where this box's elevation came from, if a source
+    /// layer has been added.
+    pub fn source(&self) -> Option<Source> {
+        self.source
+    }
+
+    /// Returns this box's slope, in the units the slope layer was
+    /// computed in.
+    ///
+    /// Returns `None` if the slope is undefined, e.g. over a void, or
+    /// no slope layer has been added; see [`NASADEM::add_slope`].
+    pub fn slope(&self) -> Option<f32> {
+        self.slope.filter(|s| !s.is_nan())
+    }
+
+    /// Returns the direction this box's slope faces, in degrees
+    /// clockwise from north.
+    ///
+    /// Returns `None` if the aspect is undefined, e.g. on flat ground,
+    /// or no aspect layer has been added; see [`NASADEM::add_aspect`].
+    pub fn aspect(&self) -> Option<f32> {
+        self.aspect.filter(|a| !a.is_nan())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hextree::{compaction::EqCompactor, h3ron, HexTreeMap};
-    use std::{
-        fs::File,
-        io::{BufReader, BufWriter},
-    };
+    use std::{fs::File, io::BufReader};
 
     #[test]
     fn test_new() {
@@ -176,59 +535,275 @@ mod tests {
 
         let mut iter = dem.iter();
         let dbox_0_0 = iter.next().unwrap();
+        assert_eq!(dbox_0_0.center(), Point::new(-106.0, 39.0));
         assert_eq!(
             dbox_0_0.southwest_corner(),
-            &Point::new(-106.0, 38.99972229936129)
+            &Point::new(-106.0 - 0.5 / 3600.0, 39.0 - 0.5 / 3600.0)
         );
     }
 
     #[test]
-    fn test_hex_map() {
-        let elevation_src = BufReader::new(
-            File::open(format!(
-                "{}/local/NASADEM_HGT_n38w106/n38w106.hgt",
-                std::env!("CARGO_MANIFEST_DIR")
-            ))
-            .unwrap(),
-        );
+    fn test_from_tile_name() {
+        let dem = NASADEM::from_tile_name("NASADEM_HGT_n38w106/n38w106.hgt").unwrap();
+        assert_eq!(dem.southwest_corner(), &Point::new(-106, 38));
+        assert_eq!(dem.void_count(), None);
+        assert!(matches!(
+            NASADEM::from_tile_name("x38w106.hgt"),
+            Err(Error::BadFilename(_))
+        ));
+    }
+
+    #[test]
+    fn test_signed_elevation_and_voids() {
+        let mut samples = vec![0_i16; 3601 * 3601];
+        samples[0] = -415;
+        samples[1] = VOID;
+        samples[3601 * 3601 - 1] = VOID;
+        let hgt: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
 
+        let mut dem = NASADEM::new(Point::new(35, 31));
+        assert_eq!(dem.void_count(), None);
+        dem.add_elevation(&hgt[..]).unwrap();
+        assert_eq!(dem.void_count(), Some(2));
+
+        let mut iter = dem.iter();
+        let dbox = iter.next().unwrap();
+        assert_eq!(dbox.elevation(), Some(-415));
+        assert_eq!(dbox.is_void(), Some(false));
+        let dbox = iter.next().unwrap();
+        assert_eq!(dbox.elevation(), None);
+        assert_eq!(dbox.is_void(), Some(true));
+    }
+
+    #[test]
+    fn test_detect_size() {
+        let hgt = vec![0_u8; SRTM3_SIZE * SRTM3_SIZE * 2];
+        let swb = vec![0_u8; SRTM3_SIZE * SRTM3_SIZE];
         let mut dem = NASADEM::new(Point::new(-106, 38));
-        dem.add_elevation(elevation_src).unwrap();
+        dem.add_elevation(&hgt[..]).unwrap();
+        dem.add_water(&swb[..]).unwrap();
+        assert_eq!(dem.geometry().cols(), SRTM3_SIZE);
+        assert_eq!(dem.iter().count(), SRTM3_SIZE * SRTM3_SIZE);
+        let last = dem.iter().last().unwrap();
+        assert_eq!(last.center(), Point::new(-105.0, 38.0));
+
+        let srtm1_swb = vec![0_u8; SRTM1_SIZE * SRTM1_SIZE];
+        assert!(dem.add_water(&srtm1_swb[..]).is_err());
+        assert!(matches!(
+            NASADEM::new(Point::new(0, 0)).add_water(&[0_u8; 5][..]),
+            Err(Error::Truncated { expected, got: 5 }) if expected == SRTM3_SIZE * SRTM3_SIZE
+        ));
 
-        let mut elev_map = HexTreeMap::with_compactor(EqCompactor);
+        // A download cut off partway through an SRTM1 tile.
+        let expected = SRTM1_SIZE * SRTM1_SIZE * 2;
+        let truncated = vec![0_u8; expected - 1000];
+        assert!(matches!(
+            NASADEM::new(Point::new(0, 0)).add_elevation(&truncated[..]),
+            Err(Error::Truncated { expected: e, got }) if e == expected && got == expected - 1000
+        ));
+        let padded = vec![0_u8; SRTM3_SIZE * SRTM3_SIZE * 2 + 2];
+        assert!(matches!(
+            NASADEM::new(Point::new(0, 0)).add_elevation(&padded[..]),
+            Err(Error::Oversized { .. })
+        ));
+    }
 
-        let mut pre_compaction_cell_count = 0;
-        for (n, dem_box) in dem.iter().enumerate() {
-            let elev = dem_box.elevation().unwrap();
-            for cell in &h3ron::polygon_to_cells(&dem_box.polygon(), 14).unwrap() {
-                elev_map.insert(cell, elev);
-                pre_compaction_cell_count += 1;
+    #[test]
+    fn test_elevation_split_across_reads() {
+        /// Hands out three bytes per read, splitting samples.
+        struct Trickle<'a>(&'a [u8]);
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = buf.len().min(3).min(self.0.len());
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
             }
-            if n > 0 && n % 3601 == 0 {
-                println!(
-                    "sample {} ({:.02}%), total cells {}",
-                    n,
-                    (n * 100) as f64 / (3601.0 * 3601.0),
-                    pre_compaction_cell_count
-                );
+        }
+
+        let samples = [-1_i16, 2, 300, VOID, -4000, 5, 6, 7, 8];
+        let hgt: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        let mut dem = NASADEM::new(Point::new(0, 0));
+        dem.add_elevation(Trickle(&hgt)).unwrap();
+        let decoded: Vec<_> = dem.iter().map(|b| b.elevation()).collect();
+        let expected: Vec<_> = samples.iter().map(|&s| (s != VOID).then_some(s)).collect();
+        assert_eq!(decoded, expected);
+
+        let mut dem = NASADEM::with_size(Point::new(0, 0), 3);
+        assert!(matches!(
+            dem.add_elevation(Trickle(&hgt[..17])),
+            Err(Error::Truncated {
+                expected: 18,
+                got: 17
+            })
+        ));
+    }
+
+    #[test]
+    fn test_explicit_size() {
+        #[rustfmt::skip]
+        let hgt: Vec<u8> = [1_i16, 2, 3,
+                            4, 5, 6,
+                            7, 8, 9]
+            .iter()
+            .flat_map(|s| s.to_be_bytes())
+            .collect();
+        let mut dem = NASADEM::with_size(Point::new(10, -20), 3);
+        dem.add_elevation(&hgt[..]).unwrap();
+        let boxes: Vec<DEMBox> = dem.iter().collect();
+        assert_eq!(boxes.len(), 9);
+        assert_eq!(boxes[5].elevation(), Some(6));
+        assert_eq!(boxes[5].center(), Point::new(11.0, -19.5));
+        assert!(matches!(
+            NASADEM::with_size(Point::new(10, -20), 4).add_elevation(&hgt[..]),
+            Err(Error::Truncated {
+                expected: 32,
+                got: 18
+            })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_water_value() {
+        let swb = [0_u8, 255, 1, 0];
+        assert!(matches!(
+            NASADEM::new(Point::new(0, 0)).add_water(&swb[..]),
+            Err(Error::InvalidWaterValue { idx: 2, value: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_point_queries() {
+        #[rustfmt::skip]
+        let hgt: Vec<u8> = [1_i16, 2,    3,
+                            4,     VOID, 6,
+                            7,     8,    9]
+            .iter()
+            .flat_map(|s| s.to_be_bytes())
+            .collect();
+        let swb = [0_u8, 0, 0, 0, 0, 255, 0, 0, 0];
+        let mut dem = NASADEM::new(Point::new(-106, 38));
+        dem.add_elevation(&hgt[..]).unwrap();
+        assert_eq!(dem.water_at(Point::new(-105.0, 38.5)), None);
+        dem.add_water(&swb[..]).unwrap();
+
+        assert_eq!(dem.elevation_at(Point::new(-106.0, 39.0)), Some(1));
+        assert_eq!(dem.elevation_at(Point::new(-105.0, 38.0)), Some(9));
+        assert_eq!(dem.elevation_at(Point::new(-105.2, 38.9)), Some(3));
+        assert_eq!(dem.elevation_at(Point::new(-105.5, 38.5)), None);
+        assert_eq!(dem.elevation_at(Point::new(-106.1, 38.5)), None);
+        assert_eq!(dem.water_at(Point::new(-105.1, 38.6)), Some(true));
+        assert_eq!(dem.water_at(Point::new(-105.9, 38.6)), Some(false));
+        assert_eq!(dem.water_at(Point::new(-105.5, 37.9)), None);
+
+        for dem_box in dem.iter() {
+            assert_eq!(dem.elevation_at(dem_box.center()), dem_box.elevation());
+            assert_eq!(dem.water_at(dem_box.center()), dem_box.is_water());
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        // Two 3×3 tiles sharing the meridian at 0°.
+        #[rustfmt::skip]
+        let west: Vec<u8> = [10_i16, 20, 30,
+                             40,     50, 60,
+                             70,     80, 90]
+            .iter()
+            .flat_map(|s| s.to_be_bytes())
+            .collect();
+        #[rustfmt::skip]
+        let east: Vec<u8> = [30_i16, 0, 0,
+                             60,     0, 0,
+                             90,     0, 0]
+            .iter()
+            .flat_map(|s| s.to_be_bytes())
+            .collect();
+        let mut west_dem = NASADEM::new(Point::new(-1, 0));
+        west_dem.add_elevation(&west[..]).unwrap();
+        let mut east_dem = NASADEM::new(Point::new(0, 0));
+        east_dem.add_elevation(&east[..]).unwrap();
+
+        let center = Point::new(-0.75, 0.75);
+        assert_eq!(
+            west_dem.sample(Point::new(-0.9, 0.9), Interpolation::Nearest),
+            Some(10.0)
+        );
+        assert_eq!(west_dem.sample(center, Interpolation::Bilinear), Some(30.0));
+        assert_eq!(
+            west_dem.sample(Point::new(-1.5, 0.5), Interpolation::Bilinear),
+            None
+        );
+
+        for interp in [
+            Interpolation::Nearest,
+            Interpolation::Bilinear,
+            Interpolation::Bicubic,
+        ] {
+            for lat in [0.0, 0.2, 0.5, 0.75, 1.0] {
+                let edge = Point::new(0.0, lat);
+                let (w, e) = (west_dem.sample(edge, interp), east_dem.sample(edge, interp));
+                assert!(w.is_some());
+                assert_eq!(w, e, "{interp:?} at {lat}");
             }
         }
+    }
 
-        let out = BufWriter::new(
-            File::create(format!(
-                "{}/local/NASADEM_HGT_n38w106/n38w106.res14.bincode.hexmap",
-                std::env!("CARGO_MANIFEST_DIR")
-            ))
-            .unwrap(),
+    #[test]
+    fn test_window() {
+        let hgt: Vec<u8> = (0..25_i16).flat_map(|s| s.to_be_bytes()).collect();
+        let swb: Vec<u8> = (0..25).map(|i| if i == 12 { 255 } else { 0 }).collect();
+        let mut dem = NASADEM::new(Point::new(0, 0));
+        dem.add_elevation(&hgt[..]).unwrap();
+        dem.add_water(&swb[..]).unwrap();
+
+        // Cells are 0.25° wide and centered on the samples, so this
+        // touches rows 1..=3 and cols 2..=3.
+        let window = Rect::new((0.4, 0.2), (0.7, 0.7));
+        let elevations: Vec<_> = dem.iter_window(window).map(|b| b.elevation()).collect();
+        assert_eq!(
+            elevations,
+            [7, 8, 12, 13, 17, 18].map(Some),
+            "{elevations:?}"
+        );
+        assert_eq!(
+            dem.iter_window(Rect::new((2.0, 2.0), (3.0, 3.0))).count(),
+            0
         );
 
-        bincode::serialize_into(out, &elev_map).unwrap();
+        let crop = dem.crop(window).unwrap();
+        assert_eq!(crop.geometry().rows(), 3);
+        assert_eq!(crop.geometry().cols(), 2);
+        assert_eq!(crop.geometry().sample_point(0, 0), Point::new(0.5, 0.75));
+        assert_eq!(crop.southwest_corner(), &Point::new(0, 0));
+        assert_eq!(crop.water_mask().unwrap().count_water(), 1);
+        for (cropped, original) in crop.iter().zip(dem.iter_window(window)) {
+            assert_eq!(cropped.center(), original.center());
+            assert_eq!(cropped.elevation(), original.elevation());
+            assert_eq!(cropped.is_water(), original.is_water());
+            assert_eq!(cropped.polygon(), original.polygon());
+        }
+        assert_eq!(crop.elevation_at(Point::new(0.5, 0.5)), Some(12));
+        assert_eq!(crop.elevation_at(Point::new(0.25, 0.5)), None);
+        assert!(dem.crop(Rect::new((-2.0, -2.0), (-1.0, -1.0))).is_none());
+    }
 
-        println!(
-            "map len {}, total cells {}",
-            elev_map.len(),
-            pre_compaction_cell_count
+    #[test]
+    fn test_add_source() {
+        let num = [11_u8, 5, 1, 12];
+        let mut dem = NASADEM::new(Point::new(0, 0));
+        dem.add_source(&num[..]).unwrap();
+        let sources: Vec<Option<Source>> = dem.iter().map(|b| b.source()).collect();
+        assert_eq!(
+            sources,
+            [
+                Some(Source::Srtm { scenes: 1 }),
+                Some(Source::Interpolated),
+                Some(Source::AsterGdem3),
+                Some(Source::Srtm { scenes: 2 }),
+            ]
         );
-        assert!(elev_map.len() < pre_compaction_cell_count);
+        assert_eq!(dem.iter().next().unwrap().elevation(), None);
     }
 }
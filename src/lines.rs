@@ -0,0 +1,144 @@
+//! Row- and column-wise access to a tile's grid.
+
+use crate::{DEMBox, NASADEM};
+
+/// One row of a tile's samples, running west to east.
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    dem: &'a NASADEM,
+    row: usize,
+}
+
+impl<'a> Row<'a> {
+    pub(crate) fn new(dem: &'a NASADEM, row: usize) -> Self {
+        Self { dem, row }
+    }
+
+    /// Returns this row's index, counting from the north.
+    pub fn index(&self) -> usize {
+        self.row
+    }
+
+    /// Returns the latitude shared by every sample in this row.
+    pub fn latitude(&self) -> f64 {
+        self.dem.geometry.sample_point(self.row, 0).y()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dem.geometry.cols()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns this row's raw samples, voids included.
+    ///
+    /// Returns `None` if no elevation layer has been added or it is
+    /// memory-mapped; use [`Row::elevations`] to read any layer.
+    pub fn samples(&self) -> Option<&'a [i16]> {
+        let samples = self.dem.elevation.as_ref()?.as_slice()?;
+        let start = self.dem.geometry.rowcol_to_idx(self.row, 0);
+        Some(&samples[start..start + self.len()])
+    }
+
+    /// Returns the elevation of each sample, west to east.
+    ///
+    /// See [`DEMBox::elevation`].
+    pub fn elevations(&self) -> impl ExactSizeIterator<Item = Option<i16>> + 'a {
+        let (dem, row) = (self.dem, self.row);
+        (0..self.len()).map(move |col| dem.elevation_sample(row, col))
+    }
+
+    /// Returns each sample as a [`DEMBox`], west to east.
+    pub fn boxes(&self) -> impl ExactSizeIterator<Item = DEMBox> + 'a {
+        let (dem, row) = (self.dem, self.row);
+        (0..self.len()).map(move |col| dem.dem_box(row, col))
+    }
+}
+
+/// One column of a tile's samples, running north to south.
+///
+/// Columns are strided through the row-major layers, so they have no
+/// contiguous slice of samples.
+#[derive(Debug, Clone, Copy)]
+pub struct Column<'a> {
+    dem: &'a NASADEM,
+    col: usize,
+}
+
+impl<'a> Column<'a> {
+    pub(crate) fn new(dem: &'a NASADEM, col: usize) -> Self {
+        Self { dem, col }
+    }
+
+    /// Returns this column's index, counting from the west.
+    pub fn index(&self) -> usize {
+        self.col
+    }
+
+    /// Returns the longitude shared by every sample in this column.
+    pub fn longitude(&self) -> f64 {
+        self.dem.geometry.sample_point(0, self.col).x()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dem.geometry.rows()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the elevation of each sample, north to south.
+    ///
+    /// See [`DEMBox::elevation`].
+    pub fn elevations(&self) -> impl ExactSizeIterator<Item = Option<i16>> + 'a {
+        let (dem, col) = (self.dem, self.col);
+        (0..self.len()).map(move |row| dem.elevation_sample(row, col))
+    }
+
+    /// Returns each sample as a [`DEMBox`], north to south.
+    pub fn boxes(&self) -> impl ExactSizeIterator<Item = DEMBox> + 'a {
+        let (dem, col) = (self.dem, self.col);
+        (0..self.len()).map(move |row| dem.dem_box(row, col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VOID;
+    use geo_types::Point;
+
+    #[test]
+    fn test_rows_and_columns() {
+        let mut samples: Vec<i16> = (0..25).collect();
+        samples[7] = VOID;
+        let hgt: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        let mut dem = NASADEM::new(Point::new(10, -20));
+        dem.add_elevation(&hgt[..]).unwrap();
+
+        assert_eq!(dem.rows().len(), 5);
+        let row = dem.rows().nth(1).unwrap();
+        assert_eq!(row.index(), 1);
+        assert_eq!(row.latitude(), -19.25);
+        assert_eq!(row.samples(), Some(&samples[5..10]));
+        assert_eq!(
+            row.elevations().collect::<Vec<_>>(),
+            [Some(5), Some(6), None, Some(8), Some(9)]
+        );
+
+        let col = dem.cols().nth(2).unwrap();
+        assert_eq!(col.longitude(), 10.5);
+        assert_eq!(
+            col.elevations().collect::<Vec<_>>(),
+            [Some(2), None, Some(12), Some(17), Some(22)]
+        );
+        for (row_idx, dbox) in col.boxes().enumerate() {
+            assert_eq!(dbox.rowcol(), (row_idx, 2));
+            assert_eq!(dbox.center().x(), col.longitude());
+        }
+        assert!(dem.cols().nth(5).is_none());
+    }
+}
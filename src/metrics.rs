@@ -0,0 +1,283 @@
+//! Terrain indices computed from each sample's neighbourhood.
+
+use crate::{
+    terrain::{cell_size, neighbourhood},
+    Raster, NASADEM,
+};
+use geo_types::Rect;
+use std::ops::Range;
+
+/// Computes terrain indices over all or part of a tile.
+///
+/// Created by [`NASADEM::terrain_metrics`]. Every index is returned
+/// as a [`Raster`] covering the chosen window, with NaN over voids.
+/// Neighbourhoods reach outside the window into the rest of the tile.
+/// TPI, TRI and roughness leave out neighbours that are void or beyond
+/// the tile's edge, while curvature needs all of them.
+#[derive(Debug, Clone)]
+pub struct TerrainMetrics<'a> {
+    dem: &'a NASADEM,
+    rows: Range<usize>,
+    cols: Range<usize>,
+    radius: usize,
+}
+
+impl NASADEM {
+    /// Returns a calculator for terrain indices over this whole tile,
+    /// with a neighbourhood radius of one sample.
+    pub fn terrain_metrics(&self) -> TerrainMetrics<'_> {
+        TerrainMetrics {
+            dem: self,
+            rows: 0..self.geometry.rows(),
+            cols: 0..self.geometry.cols(),
+            radius: 1,
+        }
+    }
+}
+
+impl<'a> TerrainMetrics<'a> {
+    /// Sets how many samples the neighbourhood used by
+    /// [`TerrainMetrics::tpi`], [`TerrainMetrics::tri`] and
+    /// [`TerrainMetrics::roughness`] extends in each direction, so a
+    /// radius of 1 is a 3×3 window.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radius` is 0.
+    pub fn radius(mut self, radius: usize) -> Self {
+        assert!(radius > 0, "a neighbourhood needs a radius of at least 1");
+        self.radius = radius;
+        self
+    }
+
+    /// Restricts the output to the samples intersecting `window`, or
+    /// returns `None` if it doesn't overlap the tile.
+    pub fn window(mut self, window: Rect<f64>) -> Option<Self> {
+        let (rows, cols) = self.dem.geometry.window(&window)?;
+        self.rows = rows;
+        self.cols = cols;
+        Some(self)
+    }
+
+    /// Returns the curvature of the surface along the direction of
+    /// steepest slope, in 1/m.
+    ///
+    /// Positive values are convex, where flow speeds up, and negative
+    /// ones concave. Curvatures always use the 3×3 neighbourhood, and
+    /// are NaN where any of it is void or beyond the tile's edge, and
+    /// on flat ground, where the slope has no direction.
+    pub fn profile_curvature(&self) -> Raster<f32> {
+        self.map(|row, col| {
+            let [p, q, r, s, t] = self.derivatives(row, col)?;
+            let gradient = p * p + q * q;
+            let curvature = -(p * p * r + 2.0 * p * q * s + q * q * t)
+                / (gradient * (1.0 + gradient).powf(1.5));
+            Some(curvature).filter(|c| c.is_finite())
+        })
+    }
+
+    /// Returns the curvature of the contour line through each sample,
+    /// in 1/m.
+    ///
+    /// Positive values are convex, where flow spreads out, as on
+    /// spurs; negative ones concave, as in hollows. See
+    /// [`TerrainMetrics::profile_curvature`].
+    pub fn plan_curvature(&self) -> Raster<f32> {
+        self.map(|row, col| {
+            let [p, q, r, s, t] = self.derivatives(row, col)?;
+            let gradient = p * p + q * q;
+            let curvature = -(q * q * r - 2.0 * p * q * s + p * p * t) / gradient.powf(1.5);
+            Some(curvature).filter(|c| c.is_finite())
+        })
+    }
+
+    /// Returns the Topographic Position Index: each sample's elevation
+    /// minus the mean of its neighbours, in meters.
+    ///
+    /// Positive values are above their surroundings, e.g. ridges, and
+    /// negative ones below, e.g. valleys.
+    pub fn tpi(&self) -> Raster<f32> {
+        self.map(|row, col| {
+            let (center, mut sum, mut count) = (self.dem.elevation_sample(row, col)?, 0.0, 0);
+            self.for_each_neighbour(row, col, |z| {
+                sum += z;
+                count += 1;
+            });
+            (count > 0).then(|| f64::from(center) - sum / count as f64)
+        })
+    }
+
+    /// Returns the Terrain Ruggedness Index: the mean absolute
+    /// difference between each sample and its neighbours, in meters.
+    pub fn tri(&self) -> Raster<f32> {
+        self.map(|row, col| {
+            let center = f64::from(self.dem.elevation_sample(row, col)?);
+            let (mut sum, mut count) = (0.0, 0);
+            self.for_each_neighbour(row, col, |z| {
+                sum += (z - center).abs();
+                count += 1;
+            });
+            (count > 0).then(|| sum / count as f64)
+        })
+    }
+
+    /// Returns the roughness: the range of elevations in each
+    /// sample's neighbourhood, itself included, in meters.
+    pub fn roughness(&self) -> Raster<f32> {
+        self.map(|row, col| {
+            let center = f64::from(self.dem.elevation_sample(row, col)?);
+            let (mut min, mut max) = (center, center);
+            self.for_each_neighbour(row, col, |z| {
+                min = min.min(z);
+                max = max.max(z);
+            });
+            Some(max - min)
+        })
+    }
+
+    /// Returns a raster over the window, with `f` given tile
+    /// coordinates and NaN wherever it returns `None`.
+    fn map(&self, f: impl Fn(usize, usize) -> Option<f64> + Sync + Send) -> Raster<f32> {
+        let geometry = self
+            .dem
+            .geometry
+            .subgrid(self.rows.clone(), self.cols.clone());
+        let (row0, col0) = (self.rows.start, self.cols.start);
+        Raster::from_fn(geometry, |row, col| {
+            f(row0 + row, col0 + col).map_or(f32::NAN, |v| v as f32)
+        })
+    }
+
+    /// Calls `f` with the elevation of every non-void sample within
+    /// the radius of `(row, col)`, excluding itself.
+    fn for_each_neighbour(&self, row: usize, col: usize, mut f: impl FnMut(f64)) {
+        let geometry = &self.dem.geometry;
+        let rows = row.saturating_sub(self.radius)..(row + self.radius + 1).min(geometry.rows());
+        let cols = col.saturating_sub(self.radius)..(col + self.radius + 1).min(geometry.cols());
+        for r in rows {
+            for c in cols.clone() {
+                if (r, c) == (row, col) {
+                    continue;
+                }
+                if let Some(z) = self.dem.elevation_sample(r, c) {
+                    f(f64::from(z));
+                }
+            }
+        }
+    }
+
+    /// Returns the first and second derivatives `[z_x, z_y, z_xx,
+    /// z_xy, z_yy]` at `(row, col)`, with x east and y north in
+    /// meters, from the 3×3 neighbourhood, or `None` unless every
+    /// sample in it is present.
+    fn derivatives(&self, row: usize, col: usize) -> Option<[f64; 5]> {
+        // `neighbourhood` stands the center in for missing samples,
+        // which would bias the second derivatives.
+        let geometry = &self.dem.geometry;
+        if row == 0 || col == 0 || row + 1 >= geometry.rows() || col + 1 >= geometry.cols() {
+            return None;
+        }
+        let complete = (row - 1..=row + 1)
+            .all(|r| (col - 1..=col + 1).all(|c| self.dem.elevation_sample(r, c).is_some()));
+        if !complete {
+            return None;
+        }
+        let [[a, b, c], [d, e, f], [g, h, i]] = neighbourhood(self.dem, row, col)?;
+        let (dx, dy) = cell_size(self.dem, row);
+        Some([
+            (f - d) / (2.0 * dx),
+            (b - h) / (2.0 * dy),
+            (d - 2.0 * e + f) / (dx * dx),
+            (c - a - i + g) / (4.0 * dx * dy),
+            (b - 2.0 * e + h) / (dy * dy),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_support::test_tile, VOID};
+    use geo_types::Point;
+
+    #[test]
+    fn test_neighbourhood_indices() {
+        // A single 90 m peak in the middle of flat ground.
+        let dem = test_tile(5, Point::new(0, 0), |row, col| {
+            if (row, col) == (2, 2) {
+                90
+            } else {
+                0
+            }
+        });
+        let metrics = dem.terrain_metrics();
+        let tpi = metrics.tpi();
+        assert_eq!(tpi.get(2, 2), 90.0);
+        assert_eq!(tpi.get(1, 1), -90.0 / 8.0);
+        assert_eq!(tpi.get(0, 0), 0.0);
+        let tri = metrics.tri();
+        assert_eq!(tri.get(2, 2), 90.0);
+        assert_eq!(tri.get(2, 1), 90.0 / 8.0);
+        // Corner samples only have three neighbours.
+        assert_eq!(tri.get(0, 0), 0.0);
+        let roughness = metrics.roughness();
+        assert_eq!(roughness.get(1, 3), 90.0);
+        assert_eq!(roughness.get(0, 0), 0.0);
+
+        let wide = dem.terrain_metrics().radius(2);
+        assert_eq!(wide.roughness().get(0, 0), 90.0);
+        assert_eq!(wide.tpi().get(2, 2), 90.0);
+        assert_eq!(wide.tpi().get(4, 4), -90.0 / 8.0);
+    }
+
+    #[test]
+    fn test_curvature() {
+        // A ridge running north-south: convex across, straight along.
+        let ridge = test_tile(5, Point::new(0, 0), |_, col| {
+            5000 - 1000 * (col as i16 - 2).pow(2)
+        });
+        let metrics = ridge.terrain_metrics();
+        let profile = metrics.profile_curvature();
+        let plan = metrics.plan_curvature();
+        // The crest is flat in the direction of the slope.
+        assert!(profile.get(2, 2).is_nan());
+        assert!(profile.get(2, 1) > 0.0);
+        assert!(profile.get(2, 3) > 0.0);
+        assert!(plan.get(2, 1).abs() < 1e-12);
+        // The edges have no full neighbourhood.
+        assert!(profile.get(0, 1).is_nan());
+        assert!(plan.get(2, 4).is_nan());
+
+        // A bowl: concave in profile and plan.
+        let bowl = test_tile(5, Point::new(0, 0), |row, col| {
+            1000 * ((row as i16 - 2).pow(2) + (col as i16 - 2).pow(2))
+        });
+        let metrics = bowl.terrain_metrics();
+        assert!(metrics.profile_curvature().get(1, 2) < 0.0);
+        assert!(metrics.plan_curvature().get(1, 2) < 0.0);
+    }
+
+    #[test]
+    fn test_window_and_voids() {
+        let dem = test_tile(5, Point::new(0, 0), |row, col| match (row, col) {
+            (2, 2) => VOID,
+            _ => (10 * row + col) as i16,
+        });
+        let window = Rect::new((0.2, 0.2), (0.55, 0.55));
+        let metrics = dem.terrain_metrics().window(window).unwrap();
+        let tpi = metrics.tpi();
+        assert_eq!(tpi.geometry().rows(), 2);
+        assert_eq!(tpi.geometry().cols(), 2);
+        assert_eq!(tpi.geometry().sample_point(0, 0), Point::new(0.25, 0.5));
+        let full = dem.terrain_metrics().tpi();
+        assert!(tpi.get(0, 1).is_nan());
+        assert!(dem.terrain_metrics().plan_curvature().get(1, 1).is_nan());
+        assert!(!dem.terrain_metrics().tpi().get(1, 1).is_nan());
+        // Neighbours outside the window still count.
+        assert_eq!(tpi.get(1, 0), full.get(3, 1));
+        assert!(dem
+            .terrain_metrics()
+            .window(Rect::new((3.0, 3.0), (4.0, 4.0)))
+            .is_none());
+    }
+}
@@ -0,0 +1,67 @@
+//! Zero-copy elevation backed by memory-mapped HGT files.
+
+use crate::{elevation::Elevation, Result, NASADEM};
+use memmap2::Mmap;
+use std::fs::File;
+
+impl NASADEM {
+    /// Adds the elevation layer by memory-mapping an `.hgt` file
+    /// instead of reading it.
+    ///
+    /// Samples are decoded from the mapped big-endian bytes on access,
+    /// so this costs almost nothing up front, and processes mapping
+    /// the same file share its pages. [`NASADEM::void_count`] has to
+    /// scan the mapped file, unlike for layers added with
+    /// [`NASADEM::add_elevation`].
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while this tile
+    /// exists; see [`memmap2::Mmap::map`].
+    pub unsafe fn map_elevation(&mut self, file: &File) -> Result<&mut Self> {
+        let map = unsafe { Mmap::map(file)? };
+        self.check_layer_len(map.len(), 2)?;
+        self.set_elevation(Elevation::Mapped(map));
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, VOID};
+    use geo_types::Point;
+    use std::fs;
+
+    #[test]
+    fn test_map_elevation() {
+        let samples = [1_i16, -2, VOID, 400, 5, 6, 7, 8, 9];
+        let hgt: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        let path = std::env::temp_dir().join("nasadem-mmap-n00e000.hgt");
+        fs::write(&path, &hgt).unwrap();
+
+        let mut owned = NASADEM::new(Point::new(0, 0));
+        owned.add_elevation(&hgt[..]).unwrap();
+        let mut mapped = NASADEM::new(Point::new(0, 0));
+        unsafe { mapped.map_elevation(&File::open(&path).unwrap()) }.unwrap();
+
+        assert_eq!(mapped.geometry(), owned.geometry());
+        assert_eq!(mapped.void_count(), Some(1));
+        assert_eq!(mapped.heap_size(), 0);
+        let mapped_elevations: Vec<_> = mapped.iter().map(|b| b.elevation()).collect();
+        let owned_elevations: Vec<_> = owned.iter().map(|b| b.elevation()).collect();
+        assert_eq!(mapped_elevations, owned_elevations);
+        assert_eq!(mapped.elevation_at(Point::new(0.0, 0.5)), Some(400));
+
+        let mut wrong_size = NASADEM::with_size(Point::new(0, 0), 4);
+        let err = unsafe { wrong_size.map_elevation(&File::open(&path).unwrap()) }.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Truncated {
+                expected: 32,
+                got: 18
+            }
+        ));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+//! Seamless queries across many tiles.
+
+use crate::{interpolate, Interpolation, NASADEM};
+use geo_types::{Point, Rect};
+use std::collections::HashMap;
+
+/// A collection of tiles indexed by their southwest corner.
+///
+/// Queries pick whichever tile contains the point, so callers don't
+/// need to care where 1° boundaries fall. Adjacent tiles share their
+/// edge rows and columns, so points on an edge can be answered by
+/// either tile, and interpolation near an edge reaches into the
+/// neighbouring tile instead of degrading.
+#[derive(Debug, Default)]
+pub struct Mosaic {
+    tiles: HashMap<(i32, i32), NASADEM>,
+}
+
+impl Mosaic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `dem` to the mosaic, returning the tile it replaces, if
+    /// any.
+    pub fn insert(&mut self, dem: NASADEM) -> Option<NASADEM> {
+        self.tiles.insert(key(dem.southwest_corner()), dem)
+    }
+
+    pub fn remove(&mut self, southwest_corner: &Point<i32>) -> Option<NASADEM> {
+        self.tiles.remove(&key(southwest_corner))
+    }
+
+    pub fn get(&self, southwest_corner: &Point<i32>) -> Option<&NASADEM> {
+        self.tiles.get(&key(southwest_corner))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = &NASADEM> {
+        self.tiles.values()
+    }
+
+    /// Returns a tile containing `point`.
+    ///
+    /// A point on a shared edge or corner is contained by up to four
+    /// tiles; any of them that is present is returned.
+    pub fn tile_for(&self, point: Point<f64>) -> Option<&NASADEM> {
+        candidate_tiles(point).find_map(|corner| self.get(&corner))
+    }
+
+    /// Returns the elevation of the sample nearest to `point`.
+    ///
+    /// See [`NASADEM::elevation_at`].
+    pub fn elevation_at(&self, point: Point<f64>) -> Option<i16> {
+        self.tile_for(point)?.elevation_at(point)
+    }
+
+    /// Returns whether the sample nearest to `point` is water.
+    ///
+    /// See [`NASADEM::water_at`].
+    pub fn water_at(&self, point: Point<f64>) -> Option<bool> {
+        self.tile_for(point)?.water_at(point)
+    }
+
+    /// Returns the elevation at `point`, interpolated from the samples
+    /// around it, which may come from neighbouring tiles.
+    ///
+    /// See [`NASADEM::sample`].
+    pub fn sample(&self, point: Point<f64>, interp: Interpolation) -> Option<f64> {
+        let tile = self.tile_for(point)?;
+        let geometry = tile.geometry();
+        let (row, col) = geometry.fractional_rowcol(&point);
+        interpolate::interpolate(interp, row, col, |row, col| {
+            if let Some((row, col)) = geometry.checked_rowcol(row, col) {
+                return tile.elevation_sample(row, col).map(f64::from);
+            }
+            let neighbour_point = geometry.fractional_point(row as f64, col as f64);
+            let neighbour = self.tile_for(neighbour_point)?;
+            if neighbour.geometry().spacing() != geometry.spacing() {
+                // The point falls between the neighbour's samples.
+                return neighbour.sample(neighbour_point, Interpolation::Bilinear);
+            }
+            let (row, col) = neighbour.geometry().nearest_rowcol(&neighbour_point)?;
+            neighbour.elevation_sample(row, col).map(f64::from)
+        })
+    }
+
+    /// Returns the southwest corners of the tiles needed to cover
+    /// `bbox` that aren't in this mosaic.
+    ///
+    /// Corners are returned west to east, then south to north.
+    pub fn missing_tiles(&self, bbox: Rect<f64>) -> Vec<Point<i32>> {
+        tiles_covering(bbox)
+            .filter(|corner| !self.tiles.contains_key(&key(corner)))
+            .collect()
+    }
+}
+
+impl FromIterator<NASADEM> for Mosaic {
+    fn from_iter<I: IntoIterator<Item = NASADEM>>(iter: I) -> Self {
+        let mut mosaic = Self::new();
+        mosaic.extend(iter);
+        mosaic
+    }
+}
+
+impl Extend<NASADEM> for Mosaic {
+    fn extend<I: IntoIterator<Item = NASADEM>>(&mut self, iter: I) {
+        for dem in iter {
+            self.insert(dem);
+        }
+    }
+}
+
+fn key(southwest_corner: &Point<i32>) -> (i32, i32) {
+    southwest_corner.x_y()
+}
+
+/// Returns the southwest corners of the tiles that contain `point`:
+/// one for a point inside a tile, up to four on edges and corners.
+pub(crate) fn candidate_tiles(point: Point<f64>) -> impl Iterator<Item = Point<i32>> {
+    // A whole-degree coordinate is on the boundary between two tiles.
+    let span = |v: f64| (v.ceil() as i32 - 1)..=(v.floor() as i32);
+    let (x, y) = point.x_y();
+    let ys = span(y);
+    span(x).flat_map(move |x| ys.clone().map(move |y| Point::new(x, y)))
+}
+
+/// Returns the southwest corners of the tiles needed to cover `bbox`,
+/// west to east, then south to north.
+pub(crate) fn tiles_covering(bbox: Rect<f64>) -> impl Iterator<Item = Point<i32>> {
+    let span = |min: f64, max: f64| {
+        let first = min.floor() as i32;
+        first..=(max.ceil() as i32 - 1).max(first)
+    };
+    let (min, max) = (bbox.min(), bbox.max());
+    let xs = span(min.x, max.x);
+    span(min.y, max.y).flat_map(move |y| xs.clone().map(move |x| Point::new(x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_tile;
+
+    /// A 5×5 tile sampling `z = (4 * lon)²`.
+    fn parabola_tile(southwest_corner: Point<i32>) -> NASADEM {
+        let geometry = crate::Geometry::tile(&southwest_corner, 5);
+        test_tile(5, southwest_corner, |row, col| {
+            let lon = geometry.sample_point(row, col).x();
+            (16.0 * lon * lon) as i16
+        })
+    }
+
+    #[test]
+    fn test_queries_across_edges() {
+        let mosaic: Mosaic = [Point::new(-1, 0), Point::new(0, 0)]
+            .into_iter()
+            .map(parabola_tile)
+            .collect();
+        assert_eq!(mosaic.len(), 2);
+
+        assert_eq!(mosaic.elevation_at(Point::new(-0.75, 0.5)), Some(9));
+        assert_eq!(mosaic.elevation_at(Point::new(0.0, 1.0)), Some(0));
+        assert_eq!(mosaic.elevation_at(Point::new(0.5, 0.5)), Some(4));
+        assert_eq!(mosaic.elevation_at(Point::new(0.5, 1.5)), None);
+
+        // Bicubic reproduces the parabola next to the shared edge
+        // because the east tile supplies the missing column; the west
+        // tile alone has to fall back to bilinear there.
+        let point = Point::new(-0.1, 0.4);
+        let z = mosaic.sample(point, Interpolation::Bicubic).unwrap();
+        assert!((z - 0.16).abs() < 1e-9, "{z}");
+        let west = mosaic.get(&Point::new(-1, 0)).unwrap();
+        let z = west.sample(point, Interpolation::Bicubic).unwrap();
+        assert!((z - 0.4).abs() < 1e-9, "{z}");
+    }
+
+    #[test]
+    fn test_neighbours_with_different_spacing() {
+        // A fine tile next to one with half its resolution, both
+        // sampling `z = 40 * lon`.
+        let plane = |southwest_corner: Point<i32>, size| {
+            let geometry = crate::Geometry::tile(&southwest_corner, size);
+            test_tile(size, southwest_corner, |row, col| {
+                (40.0 * geometry.sample_point(row, col).x()) as i16
+            })
+        };
+        let mosaic: Mosaic = [plane(Point::new(-1, 0), 5), plane(Point::new(0, 0), 9)]
+            .into_iter()
+            .collect();
+        for x in [0.05, -0.05] {
+            let z = mosaic
+                .sample(Point::new(x, 0.4), Interpolation::Bicubic)
+                .unwrap();
+            assert!((z - 40.0 * x).abs() < 1e-9, "{z} at {x}");
+        }
+    }
+
+    #[test]
+    fn test_edges_with_one_tile() {
+        let mosaic: Mosaic = [parabola_tile(Point::new(-1, 0))].into_iter().collect();
+        let edge = Point::new(0.0, 0.5);
+        assert_eq!(mosaic.elevation_at(edge), Some(0));
+        assert_eq!(mosaic.sample(edge, Interpolation::Bicubic), Some(0.0));
+        assert_eq!(mosaic.elevation_at(Point::new(0.1, 0.5)), None);
+    }
+
+    #[test]
+    fn test_missing_tiles() {
+        let mut mosaic = Mosaic::new();
+        mosaic.insert(parabola_tile(Point::new(-1, 0)));
+        let bbox = Rect::new((-1.5, 0.5), (0.5, 1.0));
+        assert_eq!(
+            mosaic.missing_tiles(bbox),
+            [Point::new(-2, 0), Point::new(0, 0)]
+        );
+        let bbox = Rect::new((-0.5, 0.2), (-0.4, 0.3));
+        assert!(mosaic.missing_tiles(bbox).is_empty());
+        let bbox = Rect::new((1.0, 1.0), (1.0, 1.0));
+        assert_eq!(mosaic.missing_tiles(bbox), [Point::new(1, 1)]);
+    }
+}
@@ -0,0 +1,66 @@
+//! Parallel iteration with rayon.
+
+use crate::{Column, DEMBox, Row, NASADEM};
+use rayon::prelude::*;
+
+impl NASADEM {
+    /// Parallel version of [`NASADEM::iter`].
+    ///
+    /// Boxes are produced in the same row-major order when collected.
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = DEMBox> + '_ {
+        (0..self.geometry.len()).into_par_iter().map(move |idx| {
+            let (row, col) = self.geometry.idx_to_rowcol(idx);
+            self.dem_box(row, col)
+        })
+    }
+
+    /// Parallel version of [`NASADEM::rows`].
+    ///
+    /// Each row is handed to one thread, which suits scanline
+    /// algorithms that keep per-row state.
+    pub fn par_rows(&self) -> impl IndexedParallelIterator<Item = Row<'_>> + '_ {
+        (0..self.geometry.rows())
+            .into_par_iter()
+            .map(move |row| Row::new(self, row))
+    }
+
+    /// Parallel version of [`NASADEM::cols`].
+    pub fn par_cols(&self) -> impl IndexedParallelIterator<Item = Column<'_>> + '_ {
+        (0..self.geometry.cols())
+            .into_par_iter()
+            .map(move |col| Column::new(self, col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::Point;
+
+    #[test]
+    fn test_matches_sequential() {
+        let hgt: Vec<u8> = (0..101 * 101)
+            .flat_map(|s: i16| (s % 977).to_be_bytes())
+            .collect();
+        let mut dem = NASADEM::new(Point::new(7, 45));
+        dem.add_elevation(&hgt[..]).unwrap();
+
+        let sequential: Vec<_> = dem.iter().map(|b| (b.rowcol(), b.elevation())).collect();
+        let parallel: Vec<_> = dem
+            .par_iter()
+            .map(|b| (b.rowcol(), b.elevation()))
+            .collect();
+        assert_eq!(parallel, sequential);
+
+        let row_sums: Vec<i64> = dem
+            .par_rows()
+            .map(|row| row.elevations().flatten().map(i64::from).sum())
+            .collect();
+        let expected: Vec<i64> = dem
+            .rows()
+            .map(|row| row.elevations().flatten().map(i64::from).sum())
+            .collect();
+        assert_eq!(row_sums, expected);
+        assert_eq!(dem.par_cols().count(), 101);
+    }
+}
@@ -0,0 +1,186 @@
+//! Elevation profiles along great-circle paths.
+
+use crate::{
+    geodesy::{distance, intermediate_point},
+    Interpolation, Mosaic, Result, TileCache, NASADEM,
+};
+use geo_types::Point;
+
+/// One sample of an elevation profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfilePoint {
+    /// Distance from the start of the path in meters.
+    pub distance: f64,
+    pub point: Point<f64>,
+    /// Bilinearly interpolated elevation in meters, or `None` where
+    /// there is no data.
+    pub elevation: Option<f64>,
+    /// Whether the nearest sample is water, or `None` where there is
+    /// no water layer.
+    pub is_water: Option<bool>,
+}
+
+impl NASADEM {
+    /// Returns the elevation profile along the great circle from `a`
+    /// to `b`, sampled every `step_m` meters.
+    ///
+    /// The first point is `a` and the last is `b`, so the last step
+    /// may be shorter than `step_m`. Points outside this tile have no
+    /// elevation or water flag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step_m` isn't positive.
+    pub fn profile(&self, a: Point<f64>, b: Point<f64>, step_m: f64) -> Vec<ProfilePoint> {
+        profile(a, b, step_m, |distance, point| ProfilePoint {
+            distance,
+            point,
+            elevation: self.sample(point, Interpolation::Bilinear),
+            is_water: self.water_at(point),
+        })
+    }
+}
+
+impl Mosaic {
+    /// Returns the elevation profile along the great circle from `a`
+    /// to `b`, which may cross any number of tiles.
+    ///
+    /// See [`NASADEM::profile`].
+    pub fn profile(&self, a: Point<f64>, b: Point<f64>, step_m: f64) -> Vec<ProfilePoint> {
+        profile(a, b, step_m, |distance, point| ProfilePoint {
+            distance,
+            point,
+            elevation: self.sample(point, Interpolation::Bilinear),
+            is_water: self.water_at(point),
+        })
+    }
+}
+
+impl TileCache {
+    /// Returns the elevation profile along the great circle from `a`
+    /// to `b`, loading the tiles it crosses as needed.
+    ///
+    /// See [`NASADEM::profile`].
+    pub fn profile(
+        &mut self,
+        a: Point<f64>,
+        b: Point<f64>,
+        step_m: f64,
+    ) -> Result<Vec<ProfilePoint>> {
+        profile(a, b, step_m, |distance, point| {
+            Ok(ProfilePoint {
+                distance,
+                point,
+                elevation: self.sample(point, Interpolation::Bilinear)?,
+                is_water: self.water_at(point)?,
+            })
+        })
+    }
+}
+
+/// Calls `sample` with the distance and location of each point on the
+/// path from `a` to `b`, and collects the results.
+fn profile<T, C: FromIterator<T>>(
+    a: Point<f64>,
+    b: Point<f64>,
+    step_m: f64,
+    mut sample: impl FnMut(f64, Point<f64>) -> T,
+) -> C {
+    assert!(step_m > 0.0, "profile step must be positive, got {step_m}");
+    let total = distance(a, b);
+    // A step longer than the path, even an infinite one, still ends at `b`.
+    let steps = if total > 0.0 {
+        ((total / step_m).ceil() as usize).max(1)
+    } else {
+        0
+    };
+    (0..=steps)
+        .map(|i| {
+            let (distance, point) = match i {
+                0 => (0.0, a),
+                _ if i == steps => (total, b),
+                _ => {
+                    let distance = i as f64 * step_m;
+                    (distance, intermediate_point(a, b, distance / total))
+                }
+            };
+            sample(distance, point)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{geodesy::EARTH_RADIUS, test_support::test_tile};
+    use std::f64::consts::PI;
+
+    /// A 5×5 tile sampling `z = 1000 * lat`, with water if `water`.
+    fn tile(southwest_corner: Point<i32>, water: bool) -> NASADEM {
+        let geometry = crate::Geometry::tile(&southwest_corner, 5);
+        let mut dem = test_tile(5, southwest_corner, |row, col| {
+            (1000.0 * geometry.sample_point(row, col).y()) as i16
+        });
+        dem.add_water(&[if water { 255 } else { 0 }; 25][..])
+            .unwrap();
+        dem
+    }
+
+    #[test]
+    fn test_profile_across_tiles() {
+        let mosaic: Mosaic = [tile(Point::new(0, 0), false), tile(Point::new(0, 1), true)]
+            .into_iter()
+            .collect();
+        let (a, b) = (Point::new(0.5, 0.5), Point::new(0.5, 1.5));
+        let profile = mosaic.profile(a, b, 10_000.0);
+
+        // One degree of latitude is about 111 km.
+        let total = EARTH_RADIUS * PI / 180.0;
+        assert_eq!(profile.len(), 13);
+        assert_eq!(profile[0].point, a);
+        assert_eq!(profile[12].point, b);
+        assert!((profile[12].distance - total).abs() < 1e-6);
+        for (i, sample) in profile.iter().enumerate() {
+            if i < 12 {
+                assert_eq!(sample.distance, 10_000.0 * i as f64);
+            }
+            // Along a meridian, latitude grows linearly with distance.
+            let lat = 0.5 + sample.distance / total;
+            assert!((sample.point.y() - lat).abs() < 1e-9);
+            assert!((sample.point.x() - 0.5).abs() < 1e-9);
+            let z = sample.elevation.unwrap();
+            assert!((z - 1000.0 * lat).abs() < 1e-6, "{z} at {lat}");
+            assert_eq!(sample.is_water, Some(lat > 1.0));
+        }
+    }
+
+    #[test]
+    fn test_great_circle() {
+        // Between two points at 60°N the great circle bows north of
+        // the parallel.
+        let dem = tile(Point::new(0, 0), false);
+        let profile = dem.profile(Point::new(-20.0, 60.0), Point::new(20.0, 60.0), 500_000.0);
+        let middle = profile[profile.len() / 2].point;
+        assert!(middle.y() > 60.0, "{middle:?}");
+        assert!(profile.iter().all(|p| p.elevation.is_none()));
+
+        let single = dem.profile(Point::new(0.5, 0.5), Point::new(0.5, 0.5), 1.0);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].elevation, Some(500.0));
+    }
+
+    #[test]
+    fn test_step_longer_than_path() {
+        let dem = tile(Point::new(0, 0), false);
+        let (a, b) = (Point::new(0.5, 0.25), Point::new(0.5, 0.75));
+        for step_m in [1e9, f64::INFINITY] {
+            let profile = dem.profile(a, b, step_m);
+            assert_eq!(profile.len(), 2, "{step_m}");
+            assert_eq!(profile[0].point, a);
+            assert_eq!(profile[0].distance, 0.0);
+            assert_eq!(profile[1].point, b);
+            assert_eq!(profile[1].distance, distance(a, b));
+            assert_eq!(profile[1].elevation, Some(750.0));
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Multi-resolution H3 summaries of elevation.
+
+use crate::{Mosaic, Result, NASADEM};
+use geo_types::Coord;
+use hextree::h3ron::{H3Cell, Index};
+use std::collections::HashMap;
+
+/// The range of elevations in one H3 cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellRange {
+    pub min: i16,
+    pub max: i16,
+    pub mean: f64,
+    /// Number of non-void samples in the cell.
+    pub count: usize,
+}
+
+impl CellRange {
+    fn new(elevation: i16) -> Self {
+        Self {
+            min: elevation,
+            max: elevation,
+            mean: f64::from(elevation),
+            count: 1,
+        }
+    }
+
+    fn merge(&mut self, other: &CellRange) {
+        let count = self.count + other.count;
+        self.mean =
+            (self.mean * self.count as f64 + other.mean * other.count as f64) / count as f64;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.count = count;
+    }
+
+    /// Returns `true` if every sample in the cell has the same
+    /// elevation, so there is nothing to gain from its children.
+    pub fn is_flat(&self) -> bool {
+        self.min == self.max
+    }
+}
+
+/// Elevation ranges of the same area at several H3 resolutions.
+///
+/// Every cell's range covers exactly the samples of its children one
+/// resolution down, so a caller can start at the coarsest level and
+/// only drill into cells whose range matters.
+#[derive(Debug, Clone, Default)]
+pub struct HexPyramid {
+    /// Cells by resolution, finest last.
+    levels: Vec<(u8, HashMap<H3Cell, CellRange>)>,
+}
+
+impl HexPyramid {
+    /// Returns the resolutions in this pyramid, coarsest first.
+    pub fn resolutions(&self) -> impl Iterator<Item = u8> + '_ {
+        self.levels.iter().map(|(resolution, _)| *resolution)
+    }
+
+    /// Returns every cell at `resolution`, or `None` if the pyramid
+    /// doesn't have that level.
+    pub fn level(&self, resolution: u8) -> Option<&HashMap<H3Cell, CellRange>> {
+        self.levels
+            .iter()
+            .find(|(r, _)| *r == resolution)
+            .map(|(_, cells)| cells)
+    }
+
+    /// Returns the range of `cell`, at its own resolution.
+    pub fn get(&self, cell: H3Cell) -> Option<&CellRange> {
+        self.level(cell.resolution())?.get(&cell)
+    }
+
+    /// Returns the children of `cell` at the next resolution in the
+    /// pyramid that have samples.
+    pub fn children(&self, cell: H3Cell) -> Vec<(H3Cell, &CellRange)> {
+        let resolution = cell.resolution();
+        let Some((child_resolution, cells)) = self.levels.iter().find(|(r, _)| *r > resolution)
+        else {
+            return Vec::new();
+        };
+        let Ok(children) = cell.get_children(*child_resolution) else {
+            return Vec::new();
+        };
+        children
+            .iter()
+            .filter_map(|child| cells.get_key_value(&child))
+            .map(|(child, range)| (*child, range))
+            .collect()
+    }
+}
+
+/// Builds a [`HexPyramid`] spanning a range of resolutions.
+///
+/// Each non-void sample is assigned to the cell containing it at the
+/// finest resolution, then ranges are merged up through the coarser
+/// ones, so the whole pyramid takes one pass over the samples. Levels
+/// with cells smaller than the samples' boxes have gaps, so the finest
+/// resolution should be no finer than the DEM.
+#[derive(Debug, Clone)]
+pub struct PyramidBuilder {
+    coarsest: u8,
+    finest: u8,
+}
+
+impl PyramidBuilder {
+    /// # Panics
+    ///
+    /// Panics if `coarsest` is finer than `finest`.
+    pub fn new(coarsest: u8, finest: u8) -> Self {
+        assert!(
+            coarsest <= finest,
+            "resolution {coarsest} is finer than {finest}"
+        );
+        Self { coarsest, finest }
+    }
+
+    pub fn build(&self, dem: &NASADEM) -> Result<HexPyramid> {
+        let mut finest = HashMap::new();
+        self.gather(&mut finest, dem, |_, _| true)?;
+        Ok(self.roll_up(finest))
+    }
+
+    /// Builds a pyramid over every tile of `mosaic`, counting samples
+    /// on shared edges once.
+    pub fn build_mosaic(&self, mosaic: &Mosaic) -> Result<HexPyramid> {
+        let mut finest = HashMap::new();
+        for dem in mosaic.sorted_tiles() {
+            self.gather(&mut finest, dem, mosaic.owned_samples(dem))?;
+        }
+        Ok(self.roll_up(finest))
+    }
+
+    fn gather(
+        &self,
+        cells: &mut HashMap<H3Cell, CellRange>,
+        dem: &NASADEM,
+        keep: impl Fn(usize, usize) -> bool + Sync + Send,
+    ) -> Result<()> {
+        for batch in dem.row_batches() {
+            let located = dem.map_boxes(batch, |dem_box| {
+                let (row, col) = dem_box.rowcol();
+                let Some(elevation) = dem_box.elevation().filter(|_| keep(row, col)) else {
+                    return Ok(None);
+                };
+                let cell = H3Cell::from_coordinate(Coord::from(dem_box.center()), self.finest)?;
+                Ok(Some((cell, elevation)))
+            })?;
+            for (cell, elevation) in located.into_iter().flatten() {
+                let range = CellRange::new(elevation);
+                cells
+                    .entry(cell)
+                    .and_modify(|r| r.merge(&range))
+                    .or_insert(range);
+            }
+        }
+        Ok(())
+    }
+
+    fn roll_up(&self, finest: HashMap<H3Cell, CellRange>) -> HexPyramid {
+        let mut levels = vec![(self.finest, finest)];
+        for resolution in (self.coarsest..self.finest).rev() {
+            let mut parents: HashMap<H3Cell, CellRange> = HashMap::new();
+            for (cell, range) in &levels[levels.len() - 1].1 {
+                // Resolutions below a valid cell's own are always valid.
+                let parent = cell
+                    .get_parent(resolution)
+                    .expect("valid parent resolution");
+                parents
+                    .entry(parent)
+                    .and_modify(|r| r.merge(range))
+                    .or_insert(*range);
+            }
+            levels.push((resolution, parents));
+        }
+        levels.reverse();
+        HexPyramid { levels }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_support::test_tile, VOID};
+    use geo_types::Point;
+
+    /// A 21×21 tile whose samples are their column, with one void.
+    fn tile(southwest_corner: Point<i32>) -> NASADEM {
+        test_tile(21, southwest_corner, |row, col| match (row, col) {
+            (4, 16) => VOID,
+            _ => col as i16,
+        })
+    }
+
+    #[test]
+    fn test_pyramid() {
+        let dem = tile(Point::new(-100, 40));
+        let pyramid = PyramidBuilder::new(0, 5).build(&dem).unwrap();
+        assert_eq!(
+            pyramid.resolutions().collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 5]
+        );
+
+        // The tile is inside a single resolution 0 cell.
+        let root = pyramid.level(0).unwrap();
+        assert_eq!(root.len(), 1);
+        let (&cell, range) = root.iter().next().unwrap();
+        assert_eq!((range.min, range.max, range.count), (0, 20, 21 * 21 - 1));
+        assert!((range.mean - (10.0 * 441.0 - 16.0) / 440.0).abs() < 1e-9);
+        assert!(!range.is_flat());
+        assert_eq!(pyramid.get(cell), Some(range));
+
+        // Every level accounts for every sample.
+        for resolution in pyramid.resolutions() {
+            let count: usize = pyramid
+                .level(resolution)
+                .unwrap()
+                .values()
+                .map(|r| r.count)
+                .sum();
+            assert_eq!(count, 440, "resolution {resolution}");
+        }
+
+        let children = pyramid.children(cell);
+        assert!(!children.is_empty());
+        let count: usize = children.iter().map(|(_, r)| r.count).sum();
+        assert_eq!(count, 440);
+        for (child, range) in children {
+            assert_eq!(child.resolution(), 1);
+            assert!(range.min >= 0 && range.max <= 20);
+        }
+        assert!(pyramid.level(6).is_none());
+    }
+
+    #[test]
+    fn test_mosaic_pyramid() {
+        let mosaic: Mosaic = [Point::new(-100, 40), Point::new(-100, 41)]
+            .into_iter()
+            .map(tile)
+            .collect();
+        let pyramid = PyramidBuilder::new(2, 4).build_mosaic(&mosaic).unwrap();
+        let count: usize = pyramid.level(2).unwrap().values().map(|r| r.count).sum();
+        // The shared row is counted once.
+        assert_eq!(count, 2 * 441 - 21 - 2);
+
+        // An L of three tiles shares one corner between all of them.
+        let mosaic: Mosaic = [Point::new(0, 0), Point::new(1, 0), Point::new(0, -1)]
+            .into_iter()
+            .map(|corner| test_tile(3, corner, |_, _| 0))
+            .collect();
+        let pyramid = PyramidBuilder::new(0, 0).build_mosaic(&mosaic).unwrap();
+        let count: usize = pyramid.level(0).unwrap().values().map(|r| r.count).sum();
+        assert_eq!(count, 21);
+    }
+
+    #[test]
+    #[should_panic(expected = "finer")]
+    fn test_inverted_resolutions() {
+        PyramidBuilder::new(5, 3);
+    }
+}
@@ -0,0 +1,125 @@
+//! Georeferenced grids of derived values.
+
+use crate::Geometry;
+use geo_types::Point;
+use std::ops::Range;
+
+/// A grid of values laid out like a tile's samples.
+///
+/// Floating-point rasters use NaN where no value could be computed,
+/// e.g. over voids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raster<T> {
+    geometry: Geometry,
+    data: Vec<T>,
+}
+
+impl<T: Copy + Send + Sync> Raster<T> {
+    /// Returns a raster whose value at each `(row, col)` of `geometry`
+    /// is `f(row, col)`, computed on every core with the `rayon`
+    /// feature.
+    pub(crate) fn from_fn(geometry: Geometry, f: impl Fn(usize, usize) -> T + Sync + Send) -> Self {
+        let value = |idx| {
+            let (row, col) = geometry.idx_to_rowcol(idx);
+            f(row, col)
+        };
+        #[cfg(feature = "rayon")]
+        let data = {
+            use rayon::prelude::*;
+            (0..geometry.len()).into_par_iter().map(value).collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let data = (0..geometry.len()).map(value).collect();
+        Self { geometry, data }
+    }
+
+    /// Returns the geometry mapping this raster's values to
+    /// coordinates.
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    /// Returns the values in row-major order, north to south.
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<T> {
+        self.data
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> T {
+        self.data[self.geometry.rowcol_to_idx(row, col)]
+    }
+
+    /// Returns the value nearest to `point`, or `None` if `point` is
+    /// outside this raster.
+    pub fn at(&self, point: Point<f64>) -> Option<T> {
+        let (row, col) = self.geometry.nearest_rowcol(&point)?;
+        Some(self.get(row, col))
+    }
+
+    /// Returns the number of bytes this raster holds on the heap.
+    pub fn heap_size(&self) -> usize {
+        self.data.len() * std::mem::size_of::<T>()
+    }
+
+    /// Returns the given rows and columns as a raster of their own.
+    pub(crate) fn subgrid(&self, rows: Range<usize>, cols: Range<usize>) -> Self {
+        let geometry = self.geometry.subgrid(rows.clone(), cols.clone());
+        let data = rows
+            .flat_map(|row| cols.clone().map(move |col| (row, col)))
+            .map(|(row, col)| self.get(row, col))
+            .collect();
+        Self { geometry, data }
+    }
+}
+
+#[cfg(feature = "image")]
+impl Raster<u8> {
+    /// Writes this raster to `path` as an 8-bit grayscale PNG, one
+    /// pixel per value with north up.
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        image::save_buffer_with_format(
+            path,
+            &self.data,
+            self.geometry.cols() as u32,
+            self.geometry.rows() as u32,
+            image::ExtendedColorType::L8,
+            image::ImageFormat::Png,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raster() {
+        let geometry = Geometry::tile(&Point::new(0, 0), 5);
+        let raster = Raster::from_fn(geometry, |row, col| (10 * row + col) as u8);
+        assert_eq!(raster.get(2, 3), 23);
+        assert_eq!(raster.at(Point::new(0.26, 0.49)), Some(21));
+        assert_eq!(raster.at(Point::new(1.5, 0.5)), None);
+        assert_eq!(raster.heap_size(), 25);
+
+        let sub = raster.subgrid(1..3, 2..5);
+        assert_eq!(sub.data(), [12, 13, 14, 22, 23, 24]);
+        assert_eq!(sub.at(Point::new(0.5, 0.5)), Some(22));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_save_png() {
+        let geometry = Geometry::tile(&Point::new(0, 0), 5);
+        let raster = Raster::from_fn(geometry, |row, col| (50 * row + col) as u8);
+        let path = std::env::temp_dir().join("nasadem-raster.png");
+        raster.save_png(&path).unwrap();
+        let png = image::open(&path).unwrap().into_luma8();
+        assert_eq!(png.dimensions(), (5, 5));
+        assert_eq!(png.into_raw(), raster.data());
+        std::fs::remove_file(path).unwrap();
+    }
+}
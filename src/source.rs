@@ -0,0 +1,92 @@
+//! Per-sample provenance codes from NASADEM `.num` files.
+
+/// Where a sample's elevation came from.
+///
+/// Decoded from the one-byte codes in a NASADEM `.num` layer. Codes
+/// without a known meaning are kept as [`Source::Other`] so nothing is
+/// lost; [`Source::code`] always returns the original byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// Void fill from ASTER GDEM v3.
+    AsterGdem3,
+    /// Void fill from ASTER GDEM v2.
+    AsterGdem2,
+    /// Void fill interpolated from surrounding samples.
+    Interpolated,
+    /// Void fill from USGS GMTED2010.
+    Gmted2010,
+    /// Void fill from SRTM v3 (SRTMGL1).
+    SrtmV3,
+    /// Void fill from the USGS National Elevation Dataset.
+    Ned,
+    /// Measured by SRTM, averaged over `scenes` radar scenes.
+    Srtm { scenes: u8 },
+    /// A code with no documented meaning.
+    Other(u8),
+}
+
+impl Source {
+    /// Offset added to the SRTM scene count in `.num` codes.
+    const SRTM_BASE: u8 = 10;
+
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            1 => Self::AsterGdem3,
+            2 => Self::AsterGdem2,
+            5 => Self::Interpolated,
+            6 => Self::Gmted2010,
+            7 => Self::SrtmV3,
+            8 => Self::Ned,
+            c if c > Self::SRTM_BASE => Self::Srtm {
+                scenes: c - Self::SRTM_BASE,
+            },
+            c => Self::Other(c),
+        }
+    }
+
+    /// Returns the `.num` code for this source.
+    ///
+    /// Codes top out at 255, so an SRTM scene count above 245 returns
+    /// that.
+    pub fn code(&self) -> u8 {
+        match *self {
+            Self::AsterGdem3 => 1,
+            Self::AsterGdem2 => 2,
+            Self::Interpolated => 5,
+            Self::Gmted2010 => 6,
+            Self::SrtmV3 => 7,
+            Self::Ned => 8,
+            Self::Srtm { scenes } => scenes.saturating_add(Self::SRTM_BASE),
+            Self::Other(c) => c,
+        }
+    }
+
+    /// Returns `true` if the sample was measured by SRTM rather than
+    /// filled in from another source.
+    pub fn is_srtm(&self) -> bool {
+        matches!(self, Self::Srtm { .. })
+    }
+}
+
+impl From<u8> for Source {
+    fn from(code: u8) -> Self {
+        Self::from_code(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_round_trip() {
+        for code in 0..=u8::MAX {
+            assert_eq!(Source::from_code(code).code(), code);
+        }
+        assert_eq!(Source::from_code(13), Source::Srtm { scenes: 3 });
+        assert_eq!(Source::from_code(5), Source::Interpolated);
+        assert_eq!(Source::from_code(10), Source::Other(10));
+        assert!(!Source::from_code(1).is_srtm());
+        assert_eq!(Source::Srtm { scenes: 250 }.code(), u8::MAX);
+    }
+}
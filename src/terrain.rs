@@ -0,0 +1,174 @@
+//! Slope and aspect.
+
+use crate::{geodesy::meters_per_degree, Raster, NASADEM};
+
+/// Units for slope rasters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SlopeUnits {
+    /// Angle from horizontal, from 0 to 90.
+    #[default]
+    Degrees,
+    /// Rise over run times 100, so 45° is 100%.
+    Percent,
+}
+
+impl NASADEM {
+    /// Returns the slope at every sample, computed with Horn's method
+    /// from the 3×3 neighbourhood around it.
+    ///
+    /// Cell sizes are converted to meters at each row's latitude.
+    /// Neighbours that are void or beyond the tile's edge are replaced
+    /// by the center sample, and void samples get NaN.
+    pub fn slope_raster(&self, units: SlopeUnits) -> Raster<f32> {
+        Raster::from_fn(self.geometry, |row, col| {
+            let Some((dz_dx, dz_dy)) = gradient(self, row, col) else {
+                return f32::NAN;
+            };
+            let rise = dz_dx.hypot(dz_dy);
+            match units {
+                SlopeUnits::Degrees => rise.atan().to_degrees() as f32,
+                SlopeUnits::Percent => (100.0 * rise) as f32,
+            }
+        })
+    }
+
+    /// Returns the direction each sample's slope faces, in degrees
+    /// clockwise from north.
+    ///
+    /// Flat samples have no aspect and, like voids, get NaN. See
+    /// [`NASADEM::slope_raster`].
+    pub fn aspect_raster(&self) -> Raster<f32> {
+        Raster::from_fn(self.geometry, |row, col| match gradient(self, row, col) {
+            Some((dz_dx, dz_dy)) if dz_dx != 0.0 || dz_dy != 0.0 => {
+                // Downhill is against the gradient.
+                let aspect = (-dz_dx).atan2(-dz_dy).to_degrees();
+                aspect.rem_euclid(360.0) as f32
+            }
+            _ => f32::NAN,
+        })
+    }
+
+    /// Computes the slope layer, which [`DEMBox::slope`] reports.
+    ///
+    /// [`DEMBox::slope`]: crate::DEMBox::slope
+    pub fn add_slope(&mut self, units: SlopeUnits) -> &mut Self {
+        self.slope = Some(self.slope_raster(units));
+        self
+    }
+
+    /// Computes the aspect layer, which [`DEMBox::aspect`] reports.
+    ///
+    /// [`DEMBox::aspect`]: crate::DEMBox::aspect
+    pub fn add_aspect(&mut self) -> &mut Self {
+        self.aspect = Some(self.aspect_raster());
+        self
+    }
+}
+
+/// Returns the elevations around `(row, col)`, north row first, or
+/// `None` if the sample itself is void.
+///
+/// Missing neighbours are replaced by the center sample.
+pub(crate) fn neighbourhood(dem: &NASADEM, row: usize, col: usize) -> Option<[[f64; 3]; 3]> {
+    let center = f64::from(dem.elevation_sample(row, col)?);
+    let mut window = [[center; 3]; 3];
+    for (dr, window_row) in window.iter_mut().enumerate() {
+        for (dc, z) in window_row.iter_mut().enumerate() {
+            let neighbour = dem
+                .geometry
+                .checked_rowcol(
+                    row as isize + dr as isize - 1,
+                    col as isize + dc as isize - 1,
+                )
+                .and_then(|(row, col)| dem.elevation_sample(row, col));
+            if let Some(neighbour) = neighbour {
+                *z = f64::from(neighbour);
+            }
+        }
+    }
+    Some(window)
+}
+
+/// Returns the ground distance in meters between adjacent columns and
+/// adjacent rows of `row`.
+pub(crate) fn cell_size(dem: &NASADEM, row: usize) -> (f64, f64) {
+    let latitude = dem.geometry.sample_point(row, 0).y();
+    let (x, y) = meters_per_degree(latitude);
+    let spacing = dem.geometry.spacing();
+    (spacing * x, spacing * y)
+}
+
+/// Returns the rate of change of elevation eastward and northward at
+/// `(row, col)`, in meters per meter, using Horn's weights.
+pub(crate) fn gradient(dem: &NASADEM, row: usize, col: usize) -> Option<(f64, f64)> {
+    let [[a, b, c], [d, _, f], [g, h, i]] = neighbourhood(dem, row, col)?;
+    let (dx, dy) = cell_size(dem, row);
+    let dz_dx = ((c + 2.0 * f + i) - (a + 2.0 * d + g)) / (8.0 * dx);
+    let dz_dy = ((a + 2.0 * b + c) - (g + 2.0 * h + i)) / (8.0 * dy);
+    Some((dz_dx, dz_dy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_support::test_tile, VOID};
+    use geo_types::Point;
+
+    #[test]
+    fn test_slope_and_aspect() {
+        // Rising 1000 m per column to the east, so facing west.
+        let dem = test_tile(5, Point::new(0, -1), |_, col| 1000 * col as i16);
+        let slope = dem.slope_raster(SlopeUnits::Percent);
+        let degrees = dem.slope_raster(SlopeUnits::Degrees);
+        let aspect = dem.aspect_raster();
+        for row in 1..4 {
+            let (dx, _) = cell_size(&dem, row);
+            let rise = 1000.0 / dx;
+            for col in 1..4 {
+                assert!((slope.get(row, col) as f64 - 100.0 * rise).abs() < 1e-4);
+                assert!((degrees.get(row, col) as f64 - rise.atan().to_degrees()).abs() < 1e-4);
+                assert_eq!(aspect.get(row, col), 270.0);
+            }
+        }
+        // Columns are closer together away from the equator, which
+        // runs along row 0, so the same rise is steeper.
+        assert!(slope.get(4, 2) > slope.get(0, 2));
+
+        // Rising to the north, so facing south.
+        let dem = test_tile(5, Point::new(0, -1), |row, _| 1000 * (4 - row as i16));
+        assert_eq!(dem.aspect_raster().get(2, 2), 180.0);
+        assert!(test_tile(5, Point::new(0, -1), |_, _| 7)
+            .aspect_raster()
+            .get(2, 2)
+            .is_nan());
+    }
+
+    #[test]
+    fn test_voids_and_edges() {
+        let mut dem = test_tile(5, Point::new(0, -1), |row, col| {
+            if (row, col) == (2, 2) {
+                VOID
+            } else {
+                10
+            }
+        });
+        let slope = dem.slope_raster(SlopeUnits::Degrees);
+        assert!(slope.get(2, 2).is_nan());
+        assert_eq!(slope.get(2, 1), 0.0);
+        assert_eq!(slope.get(0, 0), 0.0);
+
+        dem.add_slope(SlopeUnits::Degrees).add_aspect();
+        let boxes: Vec<_> = dem.iter().collect();
+        assert_eq!(boxes[0].slope(), Some(0.0));
+        assert_eq!(boxes[0].aspect(), None);
+        assert_eq!(boxes[12].slope(), None);
+
+        let crop = dem
+            .crop(geo_types::Rect::new((0.2, -0.8), (0.8, -0.2)))
+            .unwrap();
+        assert_eq!(crop.iter().next().unwrap().slope(), Some(0.0));
+        assert!(crop
+            .iter()
+            .any(|b| b.is_void() == Some(true) && b.slope().is_none()));
+    }
+}
@@ -0,0 +1,20 @@
+//! Fixtures shared by the unit tests.
+
+use crate::NASADEM;
+use geo_types::Point;
+
+/// Returns a `size`×`size` tile whose elevation at `(row, col)` is
+/// `samples(row, col)`.
+pub(crate) fn test_tile(
+    size: usize,
+    southwest_corner: Point<i32>,
+    samples: impl Fn(usize, usize) -> i16,
+) -> NASADEM {
+    let hgt: Vec<u8> = (0..size * size)
+        .map(|idx| samples(idx / size, idx % size))
+        .flat_map(|s| s.to_be_bytes())
+        .collect();
+    let mut dem = NASADEM::new(southwest_corner);
+    dem.add_elevation(&hgt[..]).unwrap();
+    dem
+}
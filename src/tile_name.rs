@@ -0,0 +1,117 @@
+//! Tile names such as `n38w106`.
+
+use crate::{Error, Result};
+use geo_types::Point;
+use std::path::Path;
+
+/// Parses the southwest corner of the tile named by `path`.
+///
+/// Accepts the usual NASADEM/SRTM spellings of a tile, in either case
+/// and with any extension, e.g. `n38w106.hgt`, `N38W106.hgt`,
+/// `NASADEM_HGT_n38w106/n38w106.swb` or `NASADEM_HGT_n38w106.zip`.
+pub fn parse_tile_name(path: impl AsRef<Path>) -> Result<Point<i32>> {
+    let path = path.as_ref();
+    let bad = || Error::BadFilename(path.to_owned());
+    let file_name = path.file_name().and_then(|n| n.to_str()).ok_or_else(bad)?;
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    let name = stem.rsplit('_').next().unwrap_or(stem).to_ascii_lowercase();
+
+    let (lat, lon) = match (name.get(..3), name.get(3..)) {
+        (Some(lat), Some(lon)) if name.len() == 7 => (lat, lon),
+        _ => return Err(bad()),
+    };
+    let lat = parse_coord(lat, 'n', 's', 89).ok_or_else(bad)?;
+    let lon = parse_coord(lon, 'e', 'w', 179).ok_or_else(bad)?;
+    Ok(Point::new(lon, lat))
+}
+
+/// Returns the lowercase name of the tile whose southwest corner is
+/// `southwest_corner`, e.g. `n38w106`.
+pub fn tile_name(southwest_corner: &Point<i32>) -> String {
+    let (lon, lat) = southwest_corner.x_y();
+    format!(
+        "{}{:02}{}{:03}",
+        if lat < 0 { 's' } else { 'n' },
+        lat.unsigned_abs(),
+        if lon < 0 { 'w' } else { 'e' },
+        lon.unsigned_abs(),
+    )
+}
+
+/// Parses a hemisphere letter followed by whole degrees.
+///
+/// Tiles are named after their southwest corner, so the positive
+/// hemisphere allows up to `max` and the negative one up to `max + 1`.
+fn parse_coord(s: &str, positive: char, negative: char, max: i32) -> Option<i32> {
+    let mut chars = s.chars();
+    let hemisphere = chars.next()?;
+    let digits = chars.as_str();
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let degrees: i32 = digits.parse().ok()?;
+    match hemisphere {
+        h if h == positive && degrees <= max => Some(degrees),
+        h if h == negative && (1..=max + 1).contains(&degrees) => Some(-degrees),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tile_name() {
+        for name in [
+            "n38w106.hgt",
+            "N38W106.hgt",
+            "NASADEM_HGT_n38w106/n38w106.swb",
+            "/data/NASADEM_HGT_n38w106.zip",
+            "N38W106.SRTMGL1.hgt.zip",
+            "n38w106",
+        ] {
+            assert_eq!(
+                parse_tile_name(name).unwrap(),
+                Point::new(-106, 38),
+                "{name}"
+            );
+        }
+        assert_eq!(parse_tile_name("s01e000.hgt").unwrap(), Point::new(0, -1));
+        assert_eq!(
+            parse_tile_name("S90W180.hgt").unwrap(),
+            Point::new(-180, -90)
+        );
+        assert_eq!(parse_tile_name("n89e179.num").unwrap(), Point::new(179, 89));
+    }
+
+    #[test]
+    fn test_tile_name() {
+        assert_eq!(tile_name(&Point::new(-106, 38)), "n38w106");
+        assert_eq!(tile_name(&Point::new(7, -1)), "s01e007");
+        for name in ["n00e000", "s90w180", "n89e179", "s12e034"] {
+            assert_eq!(tile_name(&parse_tile_name(name).unwrap()), name);
+        }
+    }
+
+    #[test]
+    fn test_reject_bad_names() {
+        for name in [
+            "",
+            "/",
+            "x38w106.hgt",
+            "n38x106.hgt",
+            "n90w106.hgt",
+            "n38e180.hgt",
+            "s00w106.hgt",
+            "n3Bw106.hgt",
+            "n38w1060.hgt",
+            "n+8w106.hgt",
+        ] {
+            assert!(
+                matches!(parse_tile_name(name), Err(Error::BadFilename(path)) if path == Path::new(name)),
+                "{name}"
+            );
+        }
+    }
+}
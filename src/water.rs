@@ -0,0 +1,197 @@
+//! Bit-packed storage for the water layer.
+
+use crate::{Error, Result};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// SWB byte marking water; land is 0.
+const SWB_WATER: u8 = 255;
+
+/// A land/water mask holding one bit per sample.
+///
+/// Each row starts on a fresh `u64` so rows can be counted without
+/// masking their neighbours.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaterMask {
+    rows: usize,
+    cols: usize,
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+impl WaterMask {
+    /// Returns an all-land mask.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let words_per_row = cols.div_ceil(WORD_BITS);
+        Self {
+            rows,
+            cols,
+            words_per_row,
+            bits: vec![0; rows * words_per_row],
+        }
+    }
+
+    /// Packs row-major SWB bytes, where 0 is land and 255 is water.
+    ///
+    /// `swb` must hold exactly `rows * cols` bytes.
+    pub fn from_swb(swb: &[u8], rows: usize, cols: usize) -> Result<Self> {
+        let (expected, got) = (rows * cols, swb.len());
+        if got < expected {
+            return Err(Error::Truncated { expected, got });
+        }
+        if got > expected {
+            return Err(Error::Oversized { expected, got });
+        }
+        let mut mask = Self::new(rows, cols);
+        for row in 0..rows {
+            let bytes = &swb[row * cols..][..cols];
+            let words = mask.row_words_mut(row);
+            for (word_idx, chunk) in bytes.chunks(WORD_BITS).enumerate() {
+                let mut word = 0_u64;
+                for (bit, &value) in chunk.iter().enumerate() {
+                    match value {
+                        0 => (),
+                        SWB_WATER => word |= 1 << bit,
+                        value => {
+                            let idx = row * cols + word_idx * WORD_BITS + bit;
+                            return Err(Error::InvalidWaterValue { idx, value });
+                        }
+                    }
+                }
+                words[word_idx] = word;
+            }
+        }
+        Ok(mask)
+    }
+
+    /// Unpacks this mask into row-major SWB bytes.
+    pub fn to_swb(&self) -> Vec<u8> {
+        let mut swb = Vec::with_capacity(self.rows * self.cols);
+        for row in 0..self.rows {
+            swb.extend((0..self.cols).map(|col| if self.get(row, col) { SWB_WATER } else { 0 }));
+        }
+        swb
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns `true` if the sample at `(row, col)` is water.
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        debug_assert!(row < self.rows && col < self.cols);
+        let word = self.bits[row * self.words_per_row + col / WORD_BITS];
+        word >> (col % WORD_BITS) & 1 == 1
+    }
+
+    /// Returns `true` if row-major sample `idx` is water.
+    pub fn get_idx(&self, idx: usize) -> bool {
+        self.get(idx / self.cols, idx % self.cols)
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, is_water: bool) {
+        debug_assert!(row < self.rows && col < self.cols);
+        let word = &mut self.bits[row * self.words_per_row + col / WORD_BITS];
+        let bit = 1 << (col % WORD_BITS);
+        if is_water {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    /// Returns the number of water samples.
+    pub fn count_water(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns the number of water samples in `row`.
+    pub fn count_row(&self, row: usize) -> usize {
+        self.row_words(row)
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the number of bytes this mask holds on the heap.
+    pub fn heap_size(&self) -> usize {
+        self.bits.len() * std::mem::size_of::<u64>()
+    }
+
+    fn row_words(&self, row: usize) -> &[u64] {
+        &self.bits[row * self.words_per_row..][..self.words_per_row]
+    }
+
+    fn row_words_mut(&mut self, row: usize) -> &mut [u64] {
+        &mut self.bits[row * self.words_per_row..][..self.words_per_row]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swb_round_trip() {
+        let (rows, cols) = (3, 130);
+        let swb: Vec<u8> = (0..rows * cols)
+            .map(|i| if i % 3 == 0 || i / cols == 2 { 255 } else { 0 })
+            .collect();
+        let mask = WaterMask::from_swb(&swb, rows, cols).unwrap();
+        assert_eq!(mask.to_swb(), swb);
+        assert_eq!(
+            mask.count_water(),
+            swb.iter().filter(|&&b| b == 255).count()
+        );
+        assert_eq!(mask.count_row(2), cols);
+        assert_eq!(mask.count_row(0), (0..cols).filter(|c| c % 3 == 0).count());
+        assert!(mask.get(1, 2));
+        assert!(mask.get_idx(cols + 2));
+        assert!(!mask.get(1, 0));
+        assert_eq!(mask.heap_size(), rows * 3 * 8);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut mask = WaterMask::new(2, 70);
+        mask.set(1, 69, true);
+        mask.set(0, 0, true);
+        mask.set(0, 0, false);
+        assert_eq!(mask.count_water(), 1);
+        assert_eq!(mask.count_row(0), 0);
+        assert!(mask.get(1, 69));
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let mut swb = vec![0_u8; 2 * 100];
+        swb[170] = 7;
+        assert!(matches!(
+            WaterMask::from_swb(&swb, 2, 100),
+            Err(Error::InvalidWaterValue { idx: 170, value: 7 })
+        ));
+    }
+
+    #[test]
+    fn test_wrong_length() {
+        assert!(matches!(
+            WaterMask::from_swb(&[0; 5], 2, 3),
+            Err(Error::Truncated {
+                expected: 6,
+                got: 5
+            })
+        ));
+        assert!(matches!(
+            WaterMask::from_swb(&[0; 7], 2, 3),
+            Err(Error::Oversized {
+                expected: 6,
+                got: 7
+            })
+        ));
+        assert_eq!(WaterMask::from_swb(&[], 2, 0).unwrap().count_water(), 0);
+    }
+}